@@ -17,14 +17,10 @@
 use sp_keyring::AccountKeyring;
 use substrate_subxt::{
     balances,
-    system::System,
     DefaultNodeRuntime as Runtime,
     ExtrinsicSuccess,
 };
 
-type AccountId = <Runtime as System>::AccountId;
-type Balance = <Runtime as balances::Balances>::Balance;
-
 fn main() {
     let result: Result<ExtrinsicSuccess<_>, Box<dyn std::error::Error + 'static>> =
         async_std::task::block_on(async move {
@@ -50,12 +46,9 @@ fn main() {
         });
     match result {
         Ok(extrinsic_success) => {
-            match extrinsic_success
-                .find_event::<(AccountId, AccountId, Balance, Balance)>(
-                    "Balances", "Transfer",
-                ) {
-                Some(Ok((_from, _to, value, _fees))) => {
-                    println!("Balance transfer success: value: {:?}", value)
+            match extrinsic_success.find_event::<balances::TransferEvent<Runtime>>() {
+                Some(Ok(event)) => {
+                    println!("Balance transfer success: value: {:?}", event.amount)
                 }
                 Some(Err(err)) => println!("Failed to decode code hash: {}", err),
                 None => println!("Failed to find Contracts::CodeStored Event"),
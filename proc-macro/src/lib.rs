@@ -0,0 +1,114 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Derive macros for substrate-subxt.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    DeriveInput,
+    Error,
+    Lit,
+    Meta,
+    NestedMeta,
+    Result,
+};
+
+/// Derives `substrate_subxt::Event` for a struct decoding the data of a module event.
+///
+/// The module is given with `#[event(module = "Balances")]`. The event name defaults to the
+/// name of the struct without an `Event` suffix and can be overridden with
+/// `#[event(name = "...")]`.
+#[proc_macro_derive(Event, attributes(event))]
+pub fn event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match event_impl(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn event_impl(input: &DeriveInput) -> Result<TokenStream2> {
+    let mut module = None;
+    let mut name = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("event"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    "expected `#[event(module = \"...\")]`",
+                ))
+            }
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref meta))
+                    if meta.path.is_ident("module") =>
+                {
+                    module = Some(lit_str(&meta.lit)?)
+                }
+                NestedMeta::Meta(Meta::NameValue(ref meta))
+                    if meta.path.is_ident("name") =>
+                {
+                    name = Some(lit_str(&meta.lit)?)
+                }
+                nested => {
+                    return Err(Error::new_spanned(
+                        nested,
+                        "expected `module = \"...\"` or `name = \"...\"`",
+                    ))
+                }
+            }
+        }
+    }
+
+    let ident = &input.ident;
+    let module = module.ok_or_else(|| {
+        Error::new_spanned(ident, "missing `#[event(module = \"...\")]`")
+    })?;
+    let name = name.unwrap_or_else(|| {
+        let ident = ident.to_string();
+        if ident.len() > "Event".len() && ident.ends_with("Event") {
+            ident[..ident.len() - "Event".len()].to_string()
+        } else {
+            ident
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics substrate_subxt::Event for #ident #ty_generics #where_clause {
+            const MODULE: &'static str = #module;
+            const EVENT: &'static str = #name;
+        }
+    })
+}
+
+fn lit_str(lit: &Lit) -> Result<String> {
+    match lit {
+        Lit::Str(lit) => Ok(lit.value()),
+        lit => Err(Error::new_spanned(lit, "expected a string literal")),
+    }
+}
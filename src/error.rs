@@ -41,6 +41,25 @@ pub enum Error {
     /// Error that can happen during the initial websocket handshake
     #[error("Rpc error: {0}")]
     WsHandshake(#[from] WsNewDnsError),
+    /// Invalid node url.
+    #[error("Invalid url: {0}")]
+    Url(#[from] url::ParseError),
+    /// No transport available for the url scheme.
+    #[error("Unsupported url scheme: {0}")]
+    UnsupportedScheme(String),
+    /// The transport cannot open subscriptions.
+    #[error("Subscriptions are not supported by this transport: {0}")]
+    SubscriptionsUnsupported(String),
+    /// The connection was lost and the subscription re-established, notifications may have
+    /// been missed in between.
+    #[error("Subscription re-established after the connection was lost")]
+    SubscriptionGap,
+    /// The node did not answer in time.
+    #[error("Request timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    /// A storage proof does not prove the requested value.
+    #[error("Invalid storage proof: {0}")]
+    InvalidProof(String),
     /// Serde serialization error
     #[error("Serde json error: {0}")]
     Serialization(#[from] serde_json::error::Error),
@@ -56,6 +75,16 @@ pub enum Error {
     /// Metadata error.
     #[error("Metadata error: {0}")]
     Metadata(#[from] MetadataError),
+    /// A module of the runtime failed to dispatch the extrinsic.
+    #[error("Runtime error: {module}::{error}")]
+    Runtime {
+        /// Name of the module, e.g. `Balances`.
+        module: String,
+        /// Name of the error, e.g. `InsufficientBalance`.
+        error: String,
+        /// Documentation of the error from the metadata.
+        docs: Vec<String>,
+    },
     /// Other error.
     #[error("Other error: {0}")]
     Other(String),
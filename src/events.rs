@@ -35,6 +35,10 @@ use codec::{
     Input,
     Output,
 };
+use serde::{
+    Serialize,
+    Serializer,
+};
 
 use crate::{
     frame::balances::Balances,
@@ -43,6 +47,7 @@ use crate::{
         Metadata,
         MetadataError,
     },
+    runtimes::Runtime,
     Phase,
     System,
     SystemEvent,
@@ -55,6 +60,37 @@ pub enum RuntimeEvent {
     Raw(RawEvent),
 }
 
+impl RuntimeEvent {
+    /// The name of the module the Event originated from
+    pub fn module(&self) -> &str {
+        match self {
+            RuntimeEvent::System(_) => "System",
+            RuntimeEvent::Raw(raw) => &raw.module,
+        }
+    }
+
+    /// The name of the Event
+    pub fn variant(&self) -> &str {
+        match self {
+            RuntimeEvent::System(SystemEvent::ExtrinsicSuccess(..)) => "ExtrinsicSuccess",
+            RuntimeEvent::System(SystemEvent::ExtrinsicFailed(..)) => "ExtrinsicFailed",
+            RuntimeEvent::Raw(raw) => &raw.variant,
+        }
+    }
+}
+
+/// Event together with the phase it was emitted in and its topics, as stored in
+/// `System::Events`
+#[derive(Debug)]
+pub struct EventRecord<T: System> {
+    /// The phase of the block the event was emitted in
+    pub phase: Phase,
+    /// The event
+    pub event: RuntimeEvent,
+    /// Topics the event is indexed by
+    pub topics: Vec<T::Hash>,
+}
+
 /// Raw bytes for an Event
 #[derive(Debug)]
 pub struct RawEvent {
@@ -64,6 +100,91 @@ pub struct RawEvent {
     pub variant: String,
     /// The raw Event data
     pub data: Vec<u8>,
+    /// The Event arguments, decoded according to their types in the metadata
+    pub args: Vec<EventValue>,
+}
+
+/// How the bytes of a primitive event argument are presented as an `EventValue`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PrimitiveKind {
+    Bool,
+    /// Little endian unsigned integer of up to 128 bits.
+    Uint,
+    /// Little endian signed integer of up to 128 bits.
+    Int,
+    Account,
+    Bytes,
+}
+
+impl PrimitiveKind {
+    fn value(self, bytes: Vec<u8>) -> EventValue {
+        match self {
+            PrimitiveKind::Bool => EventValue::Bool(bytes.iter().any(|b| *b != 0)),
+            PrimitiveKind::Uint if bytes.len() <= 16 => {
+                let mut buf = [0u8; 16];
+                buf[..bytes.len()].copy_from_slice(&bytes);
+                EventValue::Uint(u128::from_le_bytes(buf))
+            }
+            PrimitiveKind::Int if bytes.len() <= 16 => {
+                let negative = bytes.last().map_or(false, |b| b & 0x80 != 0);
+                let mut buf = if negative { [0xff; 16] } else { [0u8; 16] };
+                buf[..bytes.len()].copy_from_slice(&bytes);
+                EventValue::Int(i128::from_le_bytes(buf))
+            }
+            PrimitiveKind::Account => EventValue::Account(bytes),
+            _ => EventValue::Bytes(bytes),
+        }
+    }
+}
+
+/// Event argument decoded without its concrete type, from the type name in the metadata.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EventValue {
+    Bool(bool),
+    Uint(u128),
+    Int(i128),
+    /// Encoded account id.
+    Account(Vec<u8>),
+    Bytes(Vec<u8>),
+    Tuple(Vec<EventValue>),
+    Vec(Vec<EventValue>),
+    Option(Option<Box<EventValue>>),
+    Compact(u128),
+}
+
+/// Serializes to plain JSON-like values: numbers, `0x` prefixed hex for accounts and
+/// bytes, arrays for tuples and vecs, and `null` for `None`.
+///
+/// Integers which don't fit in 64 bits are serialized as decimal strings.
+impl Serialize for EventValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            EventValue::Bool(value) => serializer.serialize_bool(*value),
+            EventValue::Uint(value) | EventValue::Compact(value) => {
+                if *value <= u64::max_value() as u128 {
+                    serializer.serialize_u64(*value as u64)
+                } else {
+                    serializer.serialize_str(&value.to_string())
+                }
+            }
+            EventValue::Int(value) => {
+                if *value >= i64::min_value() as i128
+                    && *value <= i64::max_value() as i128
+                {
+                    serializer.serialize_i64(*value as i64)
+                } else {
+                    serializer.serialize_str(&value.to_string())
+                }
+            }
+            EventValue::Account(bytes) | EventValue::Bytes(bytes) => {
+                serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+            }
+            EventValue::Tuple(values) | EventValue::Vec(values) => {
+                values.serialize(serializer)
+            }
+            EventValue::Option(value) => value.serialize(serializer),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -74,55 +195,52 @@ pub enum EventsError {
     Metadata(#[from] MetadataError),
     #[error("Type Sizes Unavailable: {0:?}")]
     TypeSizeUnavailable(String),
+    #[error("Type Sizes Missing: {0:?}")]
+    TypeSizesMissing(Vec<String>),
 }
 
 pub struct EventsDecoder<T> {
     metadata: Metadata, // todo: [AJ] borrow?
     type_sizes: HashMap<String, usize>,
+    type_kinds: HashMap<String, PrimitiveKind>,
     marker: PhantomData<fn() -> T>,
 }
 
-impl<T: System + Balances + 'static> TryFrom<Metadata> for EventsDecoder<T> {
+impl<T: Runtime + 'static> TryFrom<Metadata> for EventsDecoder<T> {
     type Error = EventsError;
 
     fn try_from(metadata: Metadata) -> Result<Self, Self::Error> {
         let mut decoder = Self {
             metadata,
             type_sizes: HashMap::new(),
+            type_kinds: HashMap::new(),
             marker: PhantomData,
         };
         // register default event arg type sizes for dynamic decoding of events
-        decoder.register_type_size::<bool>("bool")?;
-        decoder.register_type_size::<u32>("ReferendumIndex")?;
-        decoder.register_type_size::<[u8; 16]>("Kind")?;
-        decoder.register_type_size::<[u8; 32]>("AuthorityId")?;
-        decoder.register_type_size::<u8>("u8")?;
-        decoder.register_type_size::<u32>("u32")?;
-        decoder.register_type_size::<u32>("AccountIndex")?;
-        decoder.register_type_size::<u32>("SessionIndex")?;
-        decoder.register_type_size::<u32>("PropIndex")?;
-        decoder.register_type_size::<u32>("ProposalIndex")?;
-        decoder.register_type_size::<u32>("AuthorityIndex")?;
-        decoder.register_type_size::<u64>("AuthorityWeight")?;
-        decoder.register_type_size::<u32>("MemberCount")?;
-        decoder.register_type_size::<T::AccountId>("AccountId")?;
-        decoder.register_type_size::<T::BlockNumber>("BlockNumber")?;
-        decoder.register_type_size::<T::Hash>("Hash")?;
-        decoder.register_type_size::<<T as Balances>::Balance>("Balance")?;
-        // VoteThreshold enum index
-        decoder.register_type_size::<u8>("VoteThreshold")?;
-
-        // identity
-        decoder.register_type_size::<[u8; 32]>("CatalogId")?;
+        decoder.register_type::<bool>("bool", PrimitiveKind::Bool)?;
+        decoder.register_type::<u8>("u8", PrimitiveKind::Uint)?;
+        decoder.register_type::<u32>("u32", PrimitiveKind::Uint)?;
+        decoder.register_type::<u64>("u64", PrimitiveKind::Uint)?;
+        decoder.register_type::<i64>("i64", PrimitiveKind::Int)?;
         decoder.register_type_size::<[u8; 32]>("H256")?;
-        decoder.register_type_size::<[u8; 32]>("Did")?;
-        decoder.register_type_size::<i64>("i64")?;
+        decoder.register_type::<T::AccountId>("AccountId", PrimitiveKind::Account)?;
+        decoder.register_type::<T::BlockNumber>("BlockNumber", PrimitiveKind::Uint)?;
+        decoder.register_type_size::<T::Hash>("Hash")?;
+        decoder
+            .register_type::<<T as Balances>::Balance>("Balance", PrimitiveKind::Uint)?;
+        // types of the runtime's other modules
+        T::register_type_sizes(&mut decoder)?;
 
         Ok(decoder)
     }
 }
 
-impl<T: System + Balances + 'static> EventsDecoder<T> {
+impl<T: System> EventsDecoder<T> {
+    /// The metadata events are decoded with.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
     pub fn register_type_size<U>(&mut self, name: &str) -> Result<usize, EventsError>
     where
         U: Default + Codec + Send + 'static,
@@ -136,7 +254,26 @@ impl<T: System + Balances + 'static> EventsDecoder<T> {
         }
     }
 
-    pub fn check_missing_type_sizes(&self) {
+    /// Registers the size of `U` together with how to present its bytes in an
+    /// `EventValue`.
+    pub fn register_type<U>(
+        &mut self,
+        name: &str,
+        kind: PrimitiveKind,
+    ) -> Result<usize, EventsError>
+    where
+        U: Default + Codec + Send + 'static,
+    {
+        let size = self.register_type_size::<U>(name)?;
+        self.type_kinds.insert(name.to_string(), kind);
+        Ok(size)
+    }
+}
+
+impl<T: System + Balances + 'static> EventsDecoder<T> {
+    /// Returns the event argument types without a registered size, as
+    /// `Module::Event::Type`, sorted.
+    pub fn missing_type_sizes(&self) -> Vec<String> {
         let mut missing = HashSet::new();
         for module in self.metadata.modules_with_events() {
             for event in module.events() {
@@ -157,6 +294,13 @@ impl<T: System + Balances + 'static> EventsDecoder<T> {
                 }
             }
         }
+        let mut missing = missing.into_iter().collect::<Vec<_>>();
+        missing.sort();
+        missing
+    }
+
+    pub fn check_missing_type_sizes(&self) {
+        let missing = self.missing_type_sizes();
         if missing.len() > 0 {
             log::warn!(
                 "The following primitive types do not have registered sizes: {:?} \
@@ -166,6 +310,16 @@ impl<T: System + Balances + 'static> EventsDecoder<T> {
         }
     }
 
+    /// Fails if the type of any event argument has no registered size.
+    pub fn ensure_type_sizes(&self) -> Result<(), EventsError> {
+        let missing = self.missing_type_sizes();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(EventsError::TypeSizesMissing(missing))
+        }
+    }
+
     fn decode_raw_bytes<I: Input, W: Output>(
         &self,
         args: &[EventArg],
@@ -182,10 +336,26 @@ impl<T: System + Balances + 'static> EventsDecoder<T> {
                     }
                 }
                 EventArg::Tuple(args) => self.decode_raw_bytes(args, input, output)?,
+                EventArg::Option(arg) => {
+                    let tag = input.read_byte()?;
+                    output.push_byte(tag);
+                    if tag == 1 {
+                        self.decode_raw_bytes(&[*arg.clone()], input, output)?
+                    }
+                }
+                EventArg::Compact(_) => {
+                    // integers of any size share the compact encoding
+                    <Compact<u128>>::decode(input)?.encode_to(output);
+                }
+                EventArg::Array(len, arg) => {
+                    for _ in 0..*len {
+                        self.decode_raw_bytes(&[*arg.clone()], input, output)?
+                    }
+                }
                 EventArg::Primitive(name) => {
                     if name.contains("PhantomData") {
                         // PhantomData is size 0
-                        return Ok(())
+                        continue
                     }
                     if let Some(size) = self.type_sizes.get(name) {
                         let mut buf = vec![0; *size];
@@ -200,10 +370,80 @@ impl<T: System + Balances + 'static> EventsDecoder<T> {
         Ok(())
     }
 
+    fn decode_value<I: Input>(
+        &self,
+        arg: &EventArg,
+        input: &mut I,
+    ) -> Result<EventValue, EventsError> {
+        match arg {
+            EventArg::Vec(arg) => {
+                let len = <Compact<u32>>::decode(input)?.0;
+                if is_byte(arg) {
+                    let mut bytes = vec![0; len as usize];
+                    input.read(&mut bytes)?;
+                    return Ok(EventValue::Bytes(bytes))
+                }
+                let mut values = Vec::new();
+                for _ in 0..len {
+                    values.push(self.decode_value(arg, input)?);
+                }
+                Ok(EventValue::Vec(values))
+            }
+            EventArg::Tuple(args) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.decode_value(arg, input)?);
+                }
+                Ok(EventValue::Tuple(values))
+            }
+            EventArg::Option(arg) => {
+                let value = match input.read_byte()? {
+                    0 => None,
+                    1 => Some(Box::new(self.decode_value(arg, input)?)),
+                    _ => return Err(CodecError::from("Invalid Option tag").into()),
+                };
+                Ok(EventValue::Option(value))
+            }
+            EventArg::Compact(_) => {
+                Ok(EventValue::Compact(<Compact<u128>>::decode(input)?.0))
+            }
+            EventArg::Array(len, arg) => {
+                if is_byte(arg) {
+                    let mut bytes = vec![0; *len];
+                    input.read(&mut bytes)?;
+                    return Ok(EventValue::Bytes(bytes))
+                }
+                let mut values = Vec::new();
+                for _ in 0..*len {
+                    values.push(self.decode_value(arg, input)?);
+                }
+                Ok(EventValue::Vec(values))
+            }
+            EventArg::Primitive(name) => {
+                if name.contains("PhantomData") {
+                    // PhantomData is size 0
+                    return Ok(EventValue::Tuple(Vec::new()))
+                }
+                let size = self
+                    .type_sizes
+                    .get(name)
+                    .ok_or_else(|| EventsError::TypeSizeUnavailable(name.to_owned()))?;
+                let mut bytes = vec![0; *size];
+                input.read(&mut bytes)?;
+                let kind = self
+                    .type_kinds
+                    .get(name)
+                    .cloned()
+                    .unwrap_or(PrimitiveKind::Bytes);
+                Ok(kind.value(bytes))
+            }
+        }
+    }
+
     pub fn decode_events(
         &self,
         input: &mut &[u8],
-    ) -> Result<Vec<(Phase, RuntimeEvent)>, EventsError> {
+    ) -> Result<Vec<EventRecord<T>>, EventsError> {
         let compact_len = <Compact<u32>>::decode(input)?;
         let len = compact_len.0 as usize;
 
@@ -235,17 +475,211 @@ impl<T: System + Balances + 'static> EventsDecoder<T> {
                     hex::encode(&event_data),
                 );
 
+                let mut data = &event_data[..];
+                let mut args = Vec::new();
+                for arg in event_metadata.arguments() {
+                    args.push(self.decode_value(&arg, &mut data)?);
+                }
+
                 RuntimeEvent::Raw(RawEvent {
                     module: module.name().to_string(),
                     variant: event_metadata.name.clone(),
                     data: event_data,
+                    args,
                 })
             };
 
             // topics come after the event data in EventRecord
-            let _topics = Vec::<T::Hash>::decode(input)?;
-            r.push((phase, event));
+            let topics = Vec::<T::Hash>::decode(input)?;
+            r.push(EventRecord {
+                phase,
+                event,
+                topics,
+            });
         }
         Ok(r)
     }
 }
+
+fn is_byte(arg: &EventArg) -> bool {
+    *arg == EventArg::Primitive("u8".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::Compact;
+    use sp_keyring::AccountKeyring;
+
+    use super::*;
+    use crate::{
+        metadata::fixtures,
+        tests::event_record,
+        DefaultNodeRuntime as Runtime,
+    };
+
+    fn decoder() -> EventsDecoder<Runtime> {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        EventsDecoder::try_from(metadata).unwrap()
+    }
+
+    fn encode_events(records: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut events = Compact(records.len() as u32).encode();
+        for record in records {
+            events.extend(record);
+        }
+        events
+    }
+
+    #[test]
+    fn test_decode_event_values() {
+        let alice = AccountKeyring::Alice.to_account_id();
+        let bob = AccountKeyring::Bob.to_account_id();
+        let events = encode_events(vec![event_record(
+            1,
+            (0u8, (alice.clone(), bob.clone(), 10_000u128, 1u128)),
+        )]);
+
+        let events = decoder().decode_events(&mut &events[..]).unwrap();
+        let raw = match &events[0].event {
+            RuntimeEvent::Raw(raw) => raw,
+            _ => panic!("Expected a raw event"),
+        };
+        assert_eq!(
+            (raw.module.as_str(), raw.variant.as_str()),
+            ("Balances", "Transfer")
+        );
+        assert_eq!(
+            raw.args,
+            vec![
+                EventValue::Account(alice.encode()),
+                EventValue::Account(bob.encode()),
+                EventValue::Uint(10_000),
+                EventValue::Uint(1),
+            ]
+        );
+    }
+
+    fn decode_value(ty: &str, bytes: &[u8]) -> EventValue {
+        let decoder = decoder();
+        let arg = ty.parse().unwrap();
+        let mut raw = Vec::new();
+        decoder
+            .decode_raw_bytes(&[arg.clone()], &mut &bytes[..], &mut raw)
+            .unwrap();
+        // the raw bytes are the complete encoding of the argument
+        assert_eq!(raw, bytes, "{}", ty);
+        decoder.decode_value(&arg, &mut &raw[..]).unwrap()
+    }
+
+    #[test]
+    fn test_decode_nested_values() {
+        let alice = AccountKeyring::Alice.to_account_id();
+        assert_eq!(
+            decode_value(
+                "(Vec<(AccountId, Balance)>, u32)",
+                &(vec![(alice.clone(), 5u128)], 7u32).encode()
+            ),
+            EventValue::Tuple(vec![
+                EventValue::Vec(vec![EventValue::Tuple(vec![
+                    EventValue::Account(alice.encode()),
+                    EventValue::Uint(5),
+                ])]),
+                EventValue::Uint(7),
+            ])
+        );
+        assert_eq!(
+            decode_value("Option<Vec<u8>>", &Some(vec![1u8, 2]).encode()),
+            EventValue::Option(Some(Box::new(EventValue::Bytes(vec![1, 2]))))
+        );
+        assert_eq!(
+            decode_value("Option<u32>", &Option::<u32>::None.encode()),
+            EventValue::Option(None)
+        );
+        assert_eq!(
+            decode_value("Option<bool>", &Some(false).encode()),
+            EventValue::Option(Some(Box::new(EventValue::Bool(false))))
+        );
+        assert_eq!(
+            decode_value("Compact<Balance>", &Compact(1_000_000u128).encode()),
+            EventValue::Compact(1_000_000)
+        );
+        assert_eq!(
+            decode_value("[u8; 4]", &[1u8, 2, 3, 4].encode()),
+            EventValue::Bytes(vec![1, 2, 3, 4])
+        );
+        assert_eq!(
+            decode_value("[u32; 2]", &[1u32, 2].encode()),
+            EventValue::Vec(vec![EventValue::Uint(1), EventValue::Uint(2)])
+        );
+        assert_eq!(
+            decode_value("(PhantomData<T>, u8)", &5u8.encode()),
+            EventValue::Tuple(vec![EventValue::Tuple(vec![]), EventValue::Uint(5)])
+        );
+    }
+
+    #[test]
+    fn test_primitive_kinds() {
+        assert_eq!(PrimitiveKind::Bool.value(vec![1]), EventValue::Bool(true));
+        assert_eq!(
+            PrimitiveKind::Uint.value(300u32.encode()),
+            EventValue::Uint(300)
+        );
+        assert_eq!(
+            PrimitiveKind::Int.value((-5i64).encode()),
+            EventValue::Int(-5)
+        );
+        assert_eq!(PrimitiveKind::Int.value(5i64.encode()), EventValue::Int(5));
+        // too large for an integer, kept as bytes
+        assert_eq!(
+            PrimitiveKind::Uint.value(vec![1; 32]),
+            EventValue::Bytes(vec![1; 32])
+        );
+    }
+
+    #[test]
+    fn test_serialize_event_value() {
+        let value = EventValue::Tuple(vec![
+            EventValue::Bool(true),
+            EventValue::Uint(u128::max_value()),
+            EventValue::Int(-1),
+            EventValue::Bytes(vec![0xde, 0xad]),
+            EventValue::Vec(vec![EventValue::Compact(7)]),
+            EventValue::Option(None),
+            EventValue::Option(Some(Box::new(EventValue::Uint(2)))),
+        ]);
+
+        assert_eq!(
+            serde_json::to_value(value).unwrap(),
+            serde_json::json!([
+                true,
+                u128::max_value().to_string(),
+                -1,
+                "0xdead",
+                [7],
+                null,
+                2
+            ])
+        );
+    }
+
+    #[test]
+    fn test_missing_type_sizes() {
+        assert!(decoder().ensure_type_sizes().is_ok());
+
+        let mut modules = fixtures::node_modules();
+        modules.push(fixtures::event_module(
+            "Custom",
+            vec![fixtures::event("Happened", &["AccountId", "Vec<CustomId>"])],
+        ));
+        let metadata = Metadata::decode(&fixtures::metadata(modules).encode()).unwrap();
+        let decoder = EventsDecoder::<Runtime>::try_from(metadata).unwrap();
+        assert_eq!(
+            decoder.missing_type_sizes(),
+            vec!["Custom::Happened::CustomId".to_string()]
+        );
+        match decoder.ensure_type_sizes() {
+            Err(EventsError::TypeSizesMissing(missing)) => assert_eq!(missing.len(), 1),
+            other => panic!("Expected missing type sizes, got {:?}", other),
+        }
+    }
+}
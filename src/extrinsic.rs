@@ -22,24 +22,27 @@ use codec::{
     Encode,
 };
 
-use sp_core::Pair;
+use sp_core::Bytes;
 use sp_runtime::{
     generic::{
         Era,
         SignedPayload,
         UncheckedExtrinsic,
     },
-    traits::{
-        IdentifyAccount,
-        SignedExtension,
-        Verify,
-    },
+    traits::SignedExtension,
     transaction_validity::TransactionValidityError,
 };
 
-use crate::frame::{
-    balances::Balances,
-    system::System,
+use crate::{
+    error::Error,
+    frame::{
+        balances::Balances,
+        system::System,
+        Call,
+    },
+    metadata::Metadata,
+    signer::Signer,
+    Encoded,
 };
 
 /// SignedExtra checks copied from substrate, in order to remove requirement to implement
@@ -53,10 +56,10 @@ use crate::frame::{
 /// returned via `additional_signed()`.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
 pub struct CheckVersion<T: System + Send + Sync>(
-    PhantomData<T>,
+    pub PhantomData<T>,
     /// Local version to be used for `AdditionalSigned`
     #[codec(skip)]
-    u32,
+    pub u32,
 );
 
 impl<T> SignedExtension for CheckVersion<T>
@@ -84,10 +87,10 @@ where
 /// returned via `additional_signed()`.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
 pub struct CheckGenesis<T: System + Send + Sync>(
-    PhantomData<T>,
+    pub PhantomData<T>,
     /// Local genesis hash to be used for `AdditionalSigned`
     #[codec(skip)]
-    T::Hash,
+    pub T::Hash,
 );
 
 impl<T> SignedExtension for CheckGenesis<T>
@@ -111,16 +114,16 @@ where
 ///
 /// # Note
 ///
-/// This is modified from the substrate version to allow passing in of the genesis hash, which is
-/// returned via `additional_signed()`. It assumes therefore `Era::Immortal` (The transaction is
-/// valid forever)
+/// This is modified from the substrate version to allow passing in of the hash of the block
+/// the era starts at, which is returned via `additional_signed()`. For `Era::Immortal` this
+/// is the genesis hash.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
 pub struct CheckEra<T: System + Send + Sync>(
     /// The default structure for the Extra encoding
-    (Era, PhantomData<T>),
-    /// Local genesis hash to be used for `AdditionalSigned`
+    pub (Era, PhantomData<T>),
+    /// Local era block hash to be used for `AdditionalSigned`
     #[codec(skip)]
-    T::Hash,
+    pub T::Hash,
 );
 
 impl<T> SignedExtension for CheckEra<T>
@@ -142,7 +145,7 @@ where
 
 /// Nonce check and increment to give replay protection for transactions.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
-pub struct CheckNonce<T: System + Send + Sync>(#[codec(compact)] T::Index);
+pub struct CheckNonce<T: System + Send + Sync>(#[codec(compact)] pub T::Index);
 
 impl<T> SignedExtension for CheckNonce<T>
 where
@@ -163,7 +166,7 @@ where
 
 /// Resource limit check.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
-pub struct CheckWeight<T: System + Send + Sync>(PhantomData<T>);
+pub struct CheckWeight<T: System + Send + Sync>(pub PhantomData<T>);
 
 impl<T> SignedExtension for CheckWeight<T>
 where
@@ -185,7 +188,7 @@ where
 /// Require the transactor pay for themselves and maybe include a tip to gain additional priority
 /// in the queue.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
-pub struct ChargeTransactionPayment<T: Balances>(#[codec(compact)] T::Balance);
+pub struct ChargeTransactionPayment<T: Balances>(#[codec(compact)] pub T::Balance);
 
 impl<T> SignedExtension for ChargeTransactionPayment<T>
 where
@@ -206,7 +209,7 @@ where
 
 /// Checks if a transaction would exhausts the block gas limit.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
-pub struct CheckBlockGasLimit<T: System + Send + Sync>(PhantomData<T>);
+pub struct CheckBlockGasLimit<T: System + Send + Sync>(pub PhantomData<T>);
 
 impl<T> SignedExtension for CheckBlockGasLimit<T>
 where
@@ -225,27 +228,36 @@ where
     }
 }
 
-pub trait SignedExtra<T> {
+/// The signed extensions of a runtime, built from the parameters of a transaction.
+///
+/// Runtimes name their implementation in `Runtime::SignedExtra`. The identifiers of the
+/// `Extra` extensions must match those listed in the runtime metadata.
+pub trait SignedExtra<T: Balances> {
+    /// The signed extensions, in the order of the runtime.
     type Extra: SignedExtension;
 
+    /// Creates the extra of an immortal transaction without tip.
+    fn new(version: u32, nonce: T::Index, genesis_hash: T::Hash) -> Self;
+
+    /// Sets the era of the transaction and the hash of the block it starts at.
+    fn with_era(self, era: Era, era_hash: T::Hash) -> Self;
+
+    /// Sets the tip paid to prioritise the transaction.
+    fn with_tip(self, tip: T::Balance) -> Self;
+
+    /// Returns the signed extensions.
     fn extra(&self) -> Self::Extra;
 }
 
+/// The signed extensions of the substrate `node_runtime`.
 #[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
-pub struct DefaultExtra<T: System> {
+pub struct DefaultExtra<T: Balances> {
     version: u32,
     nonce: T::Index,
     genesis_hash: T::Hash,
-}
-
-impl<T: System + Balances + Send + Sync> DefaultExtra<T> {
-    pub fn new(version: u32, nonce: T::Index, genesis_hash: T::Hash) -> Self {
-        DefaultExtra {
-            version,
-            nonce,
-            genesis_hash,
-        }
-    }
+    era: Era,
+    era_hash: T::Hash,
+    tip: T::Balance,
 }
 
 impl<T: System + Balances + Send + Sync> SignedExtra<T> for DefaultExtra<T> {
@@ -259,14 +271,36 @@ impl<T: System + Balances + Send + Sync> SignedExtra<T> for DefaultExtra<T> {
         CheckBlockGasLimit<T>,
     );
 
+    fn new(version: u32, nonce: T::Index, genesis_hash: T::Hash) -> Self {
+        DefaultExtra {
+            version,
+            nonce,
+            genesis_hash,
+            era: Era::Immortal,
+            era_hash: genesis_hash,
+            tip: Default::default(),
+        }
+    }
+
+    fn with_era(mut self, era: Era, era_hash: T::Hash) -> Self {
+        self.era = era;
+        self.era_hash = era_hash;
+        self
+    }
+
+    fn with_tip(mut self, tip: T::Balance) -> Self {
+        self.tip = tip;
+        self
+    }
+
     fn extra(&self) -> Self::Extra {
         (
             CheckVersion(PhantomData, self.version),
             CheckGenesis(PhantomData, self.genesis_hash),
-            CheckEra((Era::Immortal, PhantomData), self.genesis_hash),
+            CheckEra((self.era, PhantomData), self.era_hash),
             CheckNonce(self.nonce),
             CheckWeight(PhantomData),
-            ChargeTransactionPayment(<T as Balances>::Balance::default()),
+            ChargeTransactionPayment(self.tip),
             CheckBlockGasLimit(PhantomData),
         )
     }
@@ -288,31 +322,295 @@ impl<T: System + Balances + Send + Sync> SignedExtension for DefaultExtra<T> {
     }
 }
 
-pub fn create_and_sign<T: System + Send + Sync, C, P, S, E>(
-    signer: P,
+/// Signs `call` with `signer`, which may be a `Pair` or an external signer.
+pub async fn create_and_sign<T: Balances + Send + Sync, C, P, S, E>(
+    signer: &P,
     call: C,
     extra: E,
-) -> Result<
-    UncheckedExtrinsic<T::Address, C, S, <E as SignedExtra<T>>::Extra>,
-    TransactionValidityError,
->
+) -> Result<UncheckedExtrinsic<T::Address, C, S, <E as SignedExtra<T>>::Extra>, Error>
 where
-    P: Pair,
-    S: Verify + Codec + From<P::Signature>,
-    S::Signer: From<P::Public> + IdentifyAccount<AccountId = T::AccountId>,
+    P: Signer<T, S>,
+    S: Codec,
     C: Encode,
-    E: SignedExtra<T> + SignedExtension,
+    E: SignedExtra<T>,
     T::Address: From<T::AccountId>,
 {
     let raw_payload = SignedPayload::new(call, extra.extra())?;
-    let signature = raw_payload.using_encoded(|payload| signer.sign(payload));
+    // the payload is hashed first if it is longer than 256 bytes
+    let payload = raw_payload.using_encoded(|payload| payload.to_vec());
+    let signature = signer.sign(&payload).await?;
     let (call, extra, _) = raw_payload.deconstruct();
-    let account_id = S::Signer::from(signer.public()).into_account();
 
     Ok(UncheckedExtrinsic::new_signed(
         call,
-        account_id.into(),
-        signature.into(),
+        signer.account_id().into(),
+        signature,
         extra,
     ))
 }
+
+/// The payload of a transaction built without a connection to a node, to be signed on
+/// another, possibly air-gapped, machine.
+///
+/// Everything the node would otherwise provide comes from cached metadata and the `extra`
+/// of the transaction, e.g. `DefaultExtra::new(spec_version, nonce, genesis_hash)`.
+///
+/// While connected, cache `Client::metadata_bytes`, `Client::genesis` and
+/// `Client::runtime_version`. Offline, restore the metadata with `Metadata::decode`, build
+/// the payload with `SigningPayload::new` and export it with `to_json`. Once the signing
+/// machine has signed the `payload` bytes, assemble the extrinsic with
+/// `SigningPayload::extrinsic_from_json`, or with `into_extrinsic` if the payload was kept.
+///
+/// Wraps the `SignedPayload` of `sp_runtime`, which despite its name is the payload before
+/// it is signed, to add the export and the assembly from the signature.
+pub struct SigningPayload<T: Balances, E: SignedExtra<T>> {
+    payload: SignedPayload<Encoded, E::Extra>,
+    /// The encoded call, extra and additional signed data, for inspection.
+    parts: (Bytes, Bytes, Bytes),
+}
+
+impl<T: Balances, E: SignedExtra<T>> SigningPayload<T, E> {
+    /// Builds the payload of `call`.
+    pub fn new<C: Encode>(
+        metadata: &Metadata,
+        call: Call<C>,
+        extra: E,
+    ) -> Result<Self, Error> {
+        let call = metadata
+            .module_with_calls(call.module)
+            .and_then(|module| module.call(call.function, call.args))?;
+        let extra = extra.extra();
+        let additional_signed = extra.additional_signed()?;
+        let parts = (
+            Bytes(call.encode()),
+            Bytes(extra.encode()),
+            Bytes(additional_signed.encode()),
+        );
+        Ok(Self {
+            payload: SignedPayload::from_raw(call, extra, additional_signed),
+            parts,
+        })
+    }
+
+    /// Returns the bytes to sign.
+    ///
+    /// Payloads longer than 256 bytes are hashed first, as required by the runtime.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.payload.using_encoded(|payload| payload.to_vec())
+    }
+
+    /// Returns the bytes to sign as `0x` prefixed hex.
+    ///
+    /// The bytes may be hashed, so the extrinsic can't be assembled from them, see
+    /// `to_json`.
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.to_bytes()))
+    }
+
+    /// Returns the bytes to sign together with their parts, as `0x` prefixed hex.
+    ///
+    /// The parts let the signing machine inspect the transaction before signing it.
+    pub fn to_json(&self) -> serde_json::Value {
+        let (call, extra, additional_signed) = &self.parts;
+        serde_json::json!({
+            "call": call,
+            "extra": extra,
+            "additional_signed": additional_signed,
+            "payload": Bytes(self.to_bytes()),
+        })
+    }
+
+    /// Assembles the extrinsic from the `signature` of the payload by `account_id`.
+    pub fn into_extrinsic<S: Codec>(
+        self,
+        account_id: T::AccountId,
+        signature: S,
+    ) -> UncheckedExtrinsic<T::Address, Encoded, S, E::Extra>
+    where
+        T::Address: From<T::AccountId>,
+    {
+        let (call, extra, _) = self.payload.deconstruct();
+        UncheckedExtrinsic::new_signed(call, account_id.into(), signature, extra)
+    }
+
+    /// Assembles the extrinsic from the `signature` by `account_id` of a payload exported
+    /// with `to_json`, e.g. in another process than the one which built it.
+    ///
+    /// Only the call and extra are read, the signature is not checked against the payload.
+    pub fn extrinsic_from_json<S: Codec>(
+        json: &serde_json::Value,
+        account_id: T::AccountId,
+        signature: S,
+    ) -> Result<UncheckedExtrinsic<T::Address, Encoded, S, E::Extra>, Error>
+    where
+        T::Address: From<T::AccountId>,
+    {
+        let call: Bytes = serde_json::from_value(json["call"].clone())?;
+        let extra: Bytes = serde_json::from_value(json["extra"].clone())?;
+        let input = &mut &extra[..];
+        let extra = Decode::decode(input)?;
+        if !input.is_empty() {
+            return Err(codec::Error::from("Extra not fully consumed").into())
+        }
+        Ok(UncheckedExtrinsic::new_signed(
+            Encoded(call.0),
+            account_id.into(),
+            signature,
+            extra,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_core::Pair;
+    use sp_keyring::{
+        AccountKeyring,
+        Ed25519Keyring,
+    };
+    use sp_runtime::MultiSignature;
+
+    use super::*;
+    use crate::{
+        frame::{
+            balances,
+            system,
+        },
+        metadata::fixtures,
+        DefaultNodeRuntime as Runtime,
+    };
+
+    #[test]
+    fn test_offline_signing_payload() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let dest = AccountKeyring::Bob.to_account_id();
+        let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+        let extra = || DefaultExtra::<Runtime>::new(1, 3, [2u8; 32].into());
+
+        let payload =
+            SigningPayload::<Runtime, _>::new(&metadata, transfer(), extra()).unwrap();
+        let bytes = payload.to_bytes();
+        assert_eq!(payload.to_hex(), format!("0x{}", hex::encode(&bytes)));
+        let json = payload.to_json();
+        assert_eq!(
+            json["payload"],
+            serde_json::to_value(Bytes(bytes.clone())).unwrap()
+        );
+        // the version and genesis hash are signed but not part of the extrinsic
+        assert_eq!(
+            json["additional_signed"],
+            serde_json::to_value(Bytes(
+                (1u32, [2u8; 32], [2u8; 32], (), (), (), ()).encode()
+            ))
+            .unwrap()
+        );
+
+        // signed elsewhere, e.g. on an air-gapped machine
+        let signer = Ed25519Keyring::Alice.pair();
+        let signature = MultiSignature::from(Pair::sign(&signer, &bytes));
+        let extrinsic =
+            payload.into_extrinsic(Ed25519Keyring::Alice.to_account_id(), signature);
+
+        let call = metadata
+            .module_with_calls("Balances")
+            .and_then(|module| module.call("transfer", transfer().args))
+            .unwrap();
+        let expected = async_std::task::block_on(create_and_sign::<
+            Runtime,
+            _,
+            _,
+            MultiSignature,
+            _,
+        >(&signer, call, extra()))
+        .unwrap();
+        assert_eq!(extrinsic.encode(), expected.encode());
+    }
+
+    #[test]
+    fn test_offline_signing_payload_hashed() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let set_code = || system::set_code(vec![0; 300]);
+        let extra = || DefaultExtra::<Runtime>::new(1, 3, [2u8; 32].into());
+
+        let payload =
+            SigningPayload::<Runtime, _>::new(&metadata, set_code(), extra()).unwrap();
+        // the call alone is longer than 256 bytes, so the blake2_256 hash is signed
+        let bytes = payload.to_bytes();
+        assert_eq!(bytes.len(), 32);
+
+        let signer = Ed25519Keyring::Alice.pair();
+        let signature = MultiSignature::from(Pair::sign(&signer, &bytes));
+        let extrinsic =
+            payload.into_extrinsic(Ed25519Keyring::Alice.to_account_id(), signature);
+
+        let call = metadata
+            .module_with_calls("System")
+            .and_then(|module| module.call("set_code", set_code().args))
+            .unwrap();
+        let expected = async_std::task::block_on(create_and_sign::<
+            Runtime,
+            _,
+            _,
+            MultiSignature,
+            _,
+        >(&signer, call, extra()))
+        .unwrap();
+        assert_eq!(extrinsic.encode(), expected.encode());
+    }
+
+    #[test]
+    fn test_offline_signing_json_cycle() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let dest = AccountKeyring::Bob.to_account_id();
+        let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+        let extra = || DefaultExtra::<Runtime>::new(1, 3, [2u8; 32].into());
+
+        let exported = SigningPayload::<Runtime, _>::new(&metadata, transfer(), extra())
+            .unwrap()
+            .to_json()
+            .to_string();
+
+        // signed on an air-gapped machine, which only reads the exported json
+        let signer = Ed25519Keyring::Alice.pair();
+        let json: serde_json::Value = serde_json::from_str(&exported).unwrap();
+        let payload: Bytes = serde_json::from_value(json["payload"].clone()).unwrap();
+        let signature = MultiSignature::from(Pair::sign(&signer, &payload));
+
+        // assembled by a process which no longer has the `SigningPayload`
+        let extrinsic =
+            SigningPayload::<Runtime, DefaultExtra<Runtime>>::extrinsic_from_json(
+                &json,
+                Ed25519Keyring::Alice.to_account_id(),
+                signature,
+            )
+            .unwrap();
+
+        let call = metadata
+            .module_with_calls("Balances")
+            .and_then(|module| module.call("transfer", transfer().args))
+            .unwrap();
+        let expected = async_std::task::block_on(create_and_sign::<
+            Runtime,
+            _,
+            _,
+            MultiSignature,
+            _,
+        >(&signer, call, extra()))
+        .unwrap();
+        assert_eq!(extrinsic.encode(), expected.encode());
+
+        // the extra of another runtime doesn't decode to exactly this runtime's extra
+        let mut other = json.clone();
+        let mut extra_bytes = extra().extra().encode();
+        extra_bytes.push(0);
+        other["extra"] = serde_json::to_value(Bytes(extra_bytes)).unwrap();
+        assert!(
+            SigningPayload::<Runtime, DefaultExtra<Runtime>>::extrinsic_from_json(
+                &other,
+                Ed25519Keyring::Alice.to_account_id(),
+                MultiSignature::from(Pair::sign(&signer, &payload)),
+            )
+            .is_err()
+        );
+    }
+}
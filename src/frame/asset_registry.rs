@@ -1,14 +1,30 @@
 //! Implements support for the AssetRegistry module.
 
-use crate::frame::{
-    system::System,
-    Call,
+use crate::{
+    events::{
+        EventsDecoder,
+        EventsError,
+        PrimitiveKind,
+    },
+    frame::{
+        system::System,
+        Call,
+    },
 };
 use codec::Encode;
 /// Module name
 pub const MODULE: &str = "AssetRegistry";
 
-pub trait AssetRegistry: System {}
+pub trait AssetRegistry: System {
+    /// Registers the sizes of the types used in the AssetRegistry module events.
+    fn register_event_types(
+        decoder: &mut EventsDecoder<Self>,
+    ) -> Result<(), EventsError> {
+        decoder.register_type::<u16>("u16", PrimitiveKind::Uint)?;
+        decoder.register_type::<u128>("u128", PrimitiveKind::Uint)?;
+        Ok(())
+    }
+}
 mod calls {
     pub const CREATE_REGISTRY: &str = "create_catalog";
     pub const ADD_KYC_PROVIDER: &str = "add_kycprovider";
@@ -17,15 +33,6 @@ mod calls {
     pub const VERIFY_ASSET_PROPERTY: &str = "verify_asset_property";
     pub const REMOVE_VERIFICATION: &str = "remove_verification";
 }
-/// events
-#[allow(unused)]
-pub mod events {
-    pub const CREATED: &str = "Created";
-    pub const VERIFIED: &str = "Verified";
-    pub const ADDED: &str = "Added";
-    pub const REMOVED: &str = "Removed";
-    pub const TRANSFERED: &str = "Transfered";
-}
 #[derive(Debug, Encode, Clone, Eq, PartialEq)]
 pub enum DataType {
     Bool,
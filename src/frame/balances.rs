@@ -26,6 +26,7 @@ use futures::future::{
     Future,
 };
 
+use codec::Decode;
 use frame_support::Parameter;
 use sp_runtime::traits::{
     MaybeSerialize,
@@ -39,8 +40,10 @@ use crate::{
         system::System,
         Call,
     },
+    runtimes::Runtime,
     Client,
 };
+use substrate_subxt_proc_macro::Event;
 
 /// The subset of the `pallet_balances::Trait` that a client must implement.
 pub trait Balances: System {
@@ -85,7 +88,7 @@ pub trait BalancesStore {
     >;
 }
 
-impl<T: Balances + Sync + Send + 'static, S: 'static> BalancesStore for Client<T, S> {
+impl<T: Runtime + Sync + Send + 'static, S: 'static> BalancesStore for Client<T, S> {
     type Balances = T;
 
     fn free_balance(
@@ -142,3 +145,17 @@ pub fn transfer<T: Balances>(
 ) -> Call<TransferArgs<T>> {
     Call::new(MODULE, TRANSFER, TransferArgs { to, amount })
 }
+
+/// Transfer succeeded.
+#[derive(Clone, Debug, PartialEq, Decode, Event)]
+#[event(module = "Balances")]
+pub struct TransferEvent<T: Balances> {
+    /// Account the balance was transferred from.
+    pub from: <T as System>::AccountId,
+    /// Account the balance was transferred to.
+    pub to: <T as System>::AccountId,
+    /// Amount transferred.
+    pub amount: <T as Balances>::Balance,
+    /// Fee paid for the transfer.
+    pub fees: <T as Balances>::Balance,
+}
@@ -16,33 +16,65 @@
 
 //! Implements support for the pallet_contracts module.
 
-use crate::frame::{
-    balances::Balances,
-    system::System,
-    Call,
+use std::pin::Pin;
+
+use codec::{
+    Decode,
+    Encode,
+};
+use futures::future::{
+    self,
+    Future,
+};
+use sp_core::storage::StorageKey;
+use sp_runtime::traits::Hash;
+
+use crate::{
+    error::Error,
+    events::{
+        EventsDecoder,
+        EventsError,
+        PrimitiveKind,
+    },
+    frame::{
+        balances::Balances,
+        system::System,
+        Call,
+    },
+    rpc::ChildTrie,
+    runtimes::Runtime,
+    Client,
 };
-use codec::Encode;
+use substrate_subxt_proc_macro::Event;
 
 const MODULE: &str = "Contracts";
 
+/// Prefix of the storage key of every contract's child trie.
+const CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+
+/// Child trie type of contract storage, `ChildType::CryptoUniqueId`.
+const CHILD_TYPE_UNIQUE_ID: u32 = 1;
+
 mod calls {
     pub const PUT_CODE: &str = "put_code";
     pub const INSTANTIATE: &str = "instantiate";
     pub const CALL: &str = "call";
 }
 
-#[allow(unused)]
-mod events {
-    pub const CODE_STORED: &str = "CodeStored";
-    pub const INSTANTIATED: &str = "Instantiated";
-}
-
 /// Gas units are chosen to be represented by u64 so that gas metering
 /// instructions can operate on them efficiently.
 pub type Gas = u64;
 
 /// The subset of the `pallet_contracts::Trait` that a client must implement.
-pub trait Contracts: System + Balances {}
+pub trait Contracts: System + Balances {
+    /// Registers the sizes of the types used in the Contracts module events.
+    fn register_event_types(
+        decoder: &mut EventsDecoder<Self>,
+    ) -> Result<(), EventsError> {
+        decoder.register_type::<Gas>("Gas", PrimitiveKind::Uint)?;
+        Ok(())
+    }
+}
 
 /// Arguments for uploading contract code to the chain
 #[derive(Encode)]
@@ -73,6 +105,115 @@ pub struct CallArgs<T: Contracts> {
     data: Vec<u8>,
 }
 
+/// Leading fields of `pallet_contracts::ContractInfo`, enough to locate the storage of a
+/// contract.
+#[derive(Clone, Decode)]
+enum ContractInfo {
+    Alive { trie_id: Vec<u8> },
+    Tombstone,
+}
+
+/// Computes the trie id `pallet_contracts` assigns to a contract.
+///
+/// The runtime mixes the value of its `AccountCounter` at instantiation into the id, so
+/// `seed` must be that value. For deployed contracts prefer
+/// `ContractsStore::contract_trie_id`, which reads the id from storage.
+pub fn trie_id<T: System>(account_id: &T::AccountId, seed: u64) -> Vec<u8> {
+    let mut buf = account_id.encode();
+    buf.extend_from_slice(&seed.to_le_bytes()[..]);
+    CHILD_STORAGE_KEY_PREFIX
+        .iter()
+        .chain(<T::Hashing as Hash>::hash(&buf).as_ref())
+        .cloned()
+        .collect()
+}
+
+/// The child trie holding the storage of the contract with `trie_id`.
+pub fn child_trie(trie_id: Vec<u8>) -> ChildTrie {
+    ChildTrie {
+        storage_key: StorageKey(trie_id.clone()),
+        unique_id: StorageKey(trie_id),
+        child_type: CHILD_TYPE_UNIQUE_ID,
+    }
+}
+
+/// The key of a contract storage cell within the contract's child trie.
+pub fn storage_cell_key(key: &[u8; 32]) -> StorageKey {
+    StorageKey(sp_core::blake2_256(key).to_vec())
+}
+
+/// The Contracts extension trait for the Client.
+pub trait ContractsStore {
+    /// Contracts type.
+    type Contracts: Contracts;
+
+    /// The trie id of the contract at `account_id`, `None` if there is no live contract.
+    fn contract_trie_id(
+        &self,
+        account_id: <Self::Contracts as System>::AccountId,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Vec<u8>>, Error>> + Send>>;
+
+    /// The raw value of the storage cell `key` of the contract at `account_id`.
+    fn contract_storage(
+        &self,
+        account_id: <Self::Contracts as System>::AccountId,
+        key: [u8; 32],
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Vec<u8>>, Error>> + Send>>;
+}
+
+impl<T: Contracts + Runtime + Sync + Send + 'static, S: 'static> ContractsStore
+    for Client<T, S>
+{
+    type Contracts = T;
+
+    fn contract_trie_id(
+        &self,
+        account_id: <Self::Contracts as System>::AccountId,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Vec<u8>>, Error>> + Send>> {
+        // the entry is optional, so the default is an encoded `None`
+        let contract_info_map = || {
+            Ok(self
+                .metadata
+                .module(MODULE)?
+                .storage("ContractInfoOf")?
+                .get_map::<_, Option<ContractInfo>>()?)
+        };
+        let map = match contract_info_map() {
+            Ok(map) => map,
+            Err(err) => return Box::pin(future::err(err)),
+        };
+        let client = self.clone();
+        Box::pin(async move {
+            let info = client
+                .fetch::<ContractInfo>(map.key(account_id), None)
+                .await?;
+            match info {
+                Some(ContractInfo::Alive { trie_id }) => Ok(Some(trie_id)),
+                _ => Ok(None),
+            }
+        })
+    }
+
+    fn contract_storage(
+        &self,
+        account_id: <Self::Contracts as System>::AccountId,
+        key: [u8; 32],
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Vec<u8>>, Error>> + Send>> {
+        let client = self.clone();
+        Box::pin(async move {
+            let trie_id = match client.contract_trie_id(account_id).await? {
+                Some(trie_id) => trie_id,
+                None => return Ok(None),
+            };
+            let data = client
+                .rpc
+                .child_storage(&child_trie(trie_id), storage_cell_key(&key), None)
+                .await?;
+            Ok(data.map(|data| data.0))
+        })
+    }
+}
+
 /// Stores the given binary Wasm code into the chain's storage and returns
 /// its `codehash`.
 /// You can instantiate contracts only with stored code.
@@ -137,40 +278,58 @@ pub fn call<T: Contracts>(
     )
 }
 
+/// Code with the given hash has been stored.
+#[derive(Clone, Debug, PartialEq, Decode, Event)]
+#[event(module = "Contracts")]
+pub struct CodeStoredEvent<T: Contracts> {
+    /// Hash of the stored code.
+    pub code_hash: <T as System>::Hash,
+}
+
+/// Contract deployed by address at the specified address.
+#[derive(Clone, Debug, PartialEq, Decode, Event)]
+#[event(module = "Contracts")]
+pub struct InstantiatedEvent<T: Contracts> {
+    /// Account that instantiated the contract.
+    pub caller: <T as System>::AccountId,
+    /// Account of the new contract.
+    pub contract: <T as System>::AccountId,
+}
+
 #[cfg(test)]
 mod tests {
-    use codec::Codec;
-    use sp_core::Pair;
-    use sp_keyring::AccountKeyring;
-    use sp_runtime::traits::{
-        IdentifyAccount,
-        Verify,
+    use codec::{
+        Codec,
+        Encode,
     };
+    use sp_core::storage::StorageData;
+    use sp_keyring::AccountKeyring;
 
-    use super::events;
+    use super::{
+        trie_id,
+        CodeStoredEvent,
+        ContractsStore,
+        InstantiatedEvent,
+        CHILD_STORAGE_KEY_PREFIX,
+    };
     use crate::{
-        frame::contracts::MODULE,
-        tests::test_client,
-        Balances,
+        tests::{
+            mock_client,
+            test_client,
+        },
         Client,
         DefaultNodeRuntime as Runtime,
         Error,
-        System,
+        MockTransport,
+        Signer,
     };
 
-    type AccountId = <Runtime as System>::AccountId;
-
-    async fn put_code<T, P, S>(
-        client: &Client<T, S>,
-        signer: P,
-    ) -> Result<T::Hash, Error>
+    async fn put_code<T, P, S>(client: &Client<T, S>, signer: P) -> Result<T::Hash, Error>
     where
-        T: System + Balances + Send + Sync,
+        T: crate::runtimes::Runtime + Send + Sync,
         T::Address: From<T::AccountId>,
-        P: Pair,
-        P::Signature: Codec,
-        S: Verify + Codec + From<P::Signature> + 'static,
-        S::Signer: From<P::Public> + IdentifyAccount<AccountId = T::AccountId>,
+        P: Signer<T, S>,
+        S: Codec + 'static,
     {
         const CONTRACT: &str = r#"
 (module
@@ -184,10 +343,10 @@ mod tests {
 
         let result = xt.watch().submit(super::put_code(500_000, wasm)).await?;
         let code_hash = result
-            .find_event::<T::Hash>(MODULE, events::CODE_STORED)
+            .find_event::<CodeStoredEvent<T>>()
             .ok_or(Error::Other("Failed to find CodeStored event".into()))??;
 
-        Ok(code_hash)
+        Ok(code_hash.code_hash)
     }
 
     #[test]
@@ -230,7 +389,7 @@ mod tests {
                 ))
                 .await?;
             let event = result
-                .find_event::<(AccountId, AccountId)>(MODULE, events::INSTANTIATED)
+                .find_event::<InstantiatedEvent<Runtime>>()
                 .ok_or(Error::Other("Failed to find Instantiated event".into()))??;
             Ok(event)
         });
@@ -240,4 +399,57 @@ mod tests {
             "Contracts CodeStored event should be received and decoded"
         );
     }
+
+    #[test]
+    fn test_trie_id() {
+        let account = AccountKeyring::Alice.to_account_id();
+        let id = trie_id::<Runtime>(&account, 1);
+        assert!(id.starts_with(CHILD_STORAGE_KEY_PREFIX));
+        assert_eq!(id.len(), CHILD_STORAGE_KEY_PREFIX.len() + 32);
+        assert_eq!(id, trie_id::<Runtime>(&account, 1));
+        assert_ne!(id, trie_id::<Runtime>(&account, 2));
+    }
+
+    #[test]
+    fn test_mock_contract_storage() {
+        let transport = MockTransport::new();
+        let account = AccountKeyring::Alice.to_account_id();
+        let id = trie_id::<Runtime>(&account, 1);
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            // `ContractInfo::Alive` followed by the remaining `AliveContractInfo` fields
+            let info = (0u8, id.clone(), 0u32, [0u8; 32]).encode();
+            transport
+                .respond("state_getStorage", StorageData(info))
+                .respond("state_getChildStorage", StorageData(vec![1, 2, 3]));
+            client.contract_storage(account, [7u8; 32]).await
+        });
+
+        assert_eq!(result.unwrap(), Some(vec![1, 2, 3]));
+        let (method, params) = transport.requests().pop().unwrap();
+        assert_eq!(method, "state_getChildStorage");
+        let id = format!("0x{}", hex::encode(&id));
+        assert_eq!(params[0], id);
+        assert_eq!(params[1], id);
+        assert_eq!(params[2], 1);
+        let key = format!("0x{}", hex::encode(sp_core::blake2_256(&[7u8; 32])));
+        assert_eq!(params[3], key);
+    }
+
+    #[test]
+    fn test_mock_contract_storage_no_contract() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport.respond("state_getStorage", Option::<StorageData>::None);
+            let account = AccountKeyring::Alice.to_account_id();
+            client.contract_storage(account, [7u8; 32]).await
+        });
+
+        assert_eq!(result.unwrap(), None);
+        assert!(!transport
+            .requests()
+            .iter()
+            .any(|(method, _)| method == "state_getChildStorage"));
+    }
 }
@@ -5,9 +5,15 @@ pub mod did;
 pub mod did_property;
 pub mod fact;
 
-use crate::frame::{
-    system::System,
-    Call,
+use crate::{
+    events::{
+        EventsDecoder,
+        EventsError,
+    },
+    frame::{
+        system::System,
+        Call,
+    },
 };
 use attestation::Attestation;
 use claim::Statement;
@@ -25,7 +31,16 @@ pub type Moment = u64;
 pub type ShortName = Vec<u8>;
 pub type DidPropertyName = Vec<u8>;
 
-pub trait Identity: System {}
+pub trait Identity: System {
+    /// Registers the sizes of the types used in the Identity module events.
+    fn register_event_types(
+        decoder: &mut EventsDecoder<Self>,
+    ) -> Result<(), EventsError> {
+        decoder.register_type_size::<[u8; 32]>("CatalogId")?;
+        decoder.register_type_size::<Did>("Did")?;
+        Ok(())
+    }
+}
 mod calls {
     pub const REGISTER_DID: &str = "register_did";
     pub const REGISTER_DID_FOR: &str = "register_did_for";
@@ -48,29 +63,6 @@ mod calls {
     pub const ADD_DIDS_TO_CATALOG: &str = "add_dids_to_catalog";
     pub const REMOVE_DIDS_FROM_CATALOG: &str = "remove_dids_from_catalog";
 }
-/// events
-#[allow(unused)]
-pub mod events {
-    pub const REGISTERED: &str = "Registered";
-    pub const DID_UPDATED: &str = "DidUpdated";
-    pub const DID_REPLACED: &str = "DidReplaced";
-    pub const DID_CONTROLLERS_UPDATED: &str = "DidControllersUpdated";
-
-    pub const CLAIM_CONSUMERS_ADDED: &str = "ClaimConsumersAdded";
-    pub const CLAIM_CONSUMERS_REMOVED: &str = "ClaimConsumersRemoved";
-    pub const CLAIM_ISSUERS_ADDED: &str = "ClaimIssuersAdded";
-    pub const CLAIM_ISSUERS_REMOVED: &str = "ClaimIssuersRemoved";
-
-    pub const CLAIM_MADE: &str = "ClaimMade";
-
-    pub const CLAIM_ATTESTED: &str = "ClaimAttested";
-    pub const CLAIM_ATTESTATION_REVOKED: &str = "ClaimAttestationRevoked";
-
-    pub const CATALOG_CREATED: &str = "CatalogCreated";
-    pub const CATALOG_REMOVED: &str = "CatalogRemoved";
-    pub const CATALOG_DIDS_ADDED: &str = "CatalogDidsAdded";
-    pub const CATALOG_DIDS_REMOVED: &str = "CatalogDidsRemoved";
-}
 
 #[derive(Encode)]
 pub struct RegisterDidArgs {
@@ -16,7 +16,10 @@
 
 //! Implements support for built-in runtime modules.
 
-use codec::Encode;
+use codec::{
+    Decode,
+    Encode,
+};
 
 pub mod asset_registry;
 pub mod balances;
@@ -45,3 +48,13 @@ impl<T: Encode> Call<T> {
         }
     }
 }
+
+/// Event of a runtime module, decoded from the event data.
+///
+/// Usually implemented with `#[derive(Event)]`, see `substrate_subxt_proc_macro::Event`.
+pub trait Event: Decode {
+    /// Module name
+    const MODULE: &'static str;
+    /// Event name
+    const EVENT: &'static str;
+}
@@ -43,10 +43,8 @@ use std::{
 
 use crate::{
     error::Error,
-    frame::{
-        balances::Balances,
-        Call,
-    },
+    frame::Call,
+    runtimes::Runtime,
     Client,
 };
 
@@ -129,9 +127,7 @@ pub trait SystemStore {
     >;
 }
 
-impl<T: System + Balances + Sync + Send + 'static, S: 'static> SystemStore
-    for Client<T, S>
-{
+impl<T: Runtime + Sync + Send + 'static, S: 'static> SystemStore for Client<T, S> {
     type System = T;
 
     fn account_nonce(
@@ -183,7 +179,7 @@ pub enum SystemEvent {
 }
 
 /// A phase of a block's execution.
-#[derive(codec::Decode)]
+#[derive(Clone, Debug, PartialEq, Eq, codec::Decode)]
 pub enum Phase {
     /// Applying an extrinsic.
     ApplyExtrinsic(u32),
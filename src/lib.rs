@@ -38,9 +38,15 @@
 )]
 #![allow(clippy::type_complexity)]
 
+// Lets code generated by the derive macros name this crate from inside it.
+extern crate self as substrate_subxt;
+
 use std::{
+    collections::HashMap,
     convert::TryFrom,
     marker::PhantomData,
+    sync::Arc,
+    time::Duration,
 };
 
 use codec::{
@@ -48,20 +54,34 @@ use codec::{
     Decode,
     Encode,
 };
-use futures::future;
-use jsonrpsee::client::Subscription;
+use futures::{
+    future,
+    stream::{
+        self,
+        Stream,
+        StreamExt,
+    },
+};
 use sp_core::{
     storage::{
         StorageChangeSet,
+        StorageData,
         StorageKey,
     },
-    Pair,
+    Bytes,
 };
+use sp_rpc::number::NumberOrHex;
 use sp_runtime::{
-    generic::UncheckedExtrinsic,
+    generic::{
+        Era,
+        UncheckedExtrinsic,
+    },
     traits::{
-        IdentifyAccount,
-        Verify,
+        Header,
+        One,
+        SignedExtension,
+        UniqueSaturatedFrom,
+        UniqueSaturatedInto,
     },
     MultiSignature,
 };
@@ -74,23 +94,64 @@ mod frame;
 mod metadata;
 mod rpc;
 mod runtimes;
+mod signer;
+mod tip;
 
 pub use self::{
     error::Error,
-    events::RawEvent,
-    frame::*,
-    rpc::ExtrinsicSuccess,
-    runtimes::*,
-};
-use self::{
     events::{
+        EventRecord,
+        EventValue,
         EventsDecoder,
         EventsError,
+        PrimitiveKind,
+        RawEvent,
+        RuntimeEvent,
     },
     extrinsic::{
+        ChargeTransactionPayment,
+        CheckBlockGasLimit,
+        CheckEra,
+        CheckGenesis,
+        CheckNonce,
+        CheckVersion,
+        CheckWeight,
         DefaultExtra,
         SignedExtra,
+        SigningPayload,
     },
+    frame::*,
+    metadata::{
+        Metadata,
+        MetadataError,
+    },
+    rpc::{
+        ChildTrie,
+        ExtrinsicSuccess,
+        HttpTransport,
+        MockTransport,
+        Notifications,
+        ReadProof,
+        ReconnectPolicy,
+        ReconnectingTransport,
+        RpcFuture,
+        Subscription,
+        Transport,
+        WsTransport,
+    },
+    runtimes::*,
+    signer::{
+        SignFuture,
+        Signer,
+    },
+    tip::{
+        CongestionTip,
+        TipPolicy,
+    },
+};
+pub use substrate_subxt_proc_macro::Event;
+
+use self::{
     frame::{
         balances::Balances,
         system::{
@@ -100,7 +161,7 @@ use self::{
             SystemStore,
         },
     },
-    metadata::Metadata,
+    metadata::StorageMap,
     rpc::{
         BlockNumber,
         ChainBlock,
@@ -108,43 +169,126 @@ use self::{
     },
 };
 
+/// Number of keys requested per page by `Client::iter_map`.
+pub const STORAGE_KEYS_PAGE_SIZE: u32 = 100;
+
 /// ClientBuilder for constructing a Client.
 #[derive(Default)]
 pub struct ClientBuilder<T: System, S = MultiSignature> {
     _marker: std::marker::PhantomData<(T, S)>,
     url: Option<String>,
+    reconnect: Option<ReconnectPolicy>,
+    timeout: Option<Duration>,
+    watch_timeout: Option<Duration>,
+    rpc: Option<Rpc<T>>,
+    strict_event_types: bool,
 }
 
-impl<T: System, S> ClientBuilder<T, S> {
+impl<T: Runtime, S> ClientBuilder<T, S> {
     /// Creates a new ClientBuilder.
     pub fn new() -> Self {
         Self {
             _marker: std::marker::PhantomData,
             url: None,
+            reconnect: None,
+            timeout: None,
+            watch_timeout: None,
+            rpc: None,
+            strict_event_types: false,
         }
     }
 
     /// Set the substrate rpc address.
+    ///
+    /// The transport is selected from the url scheme, `ws://` (the default) or `http://`.
+    /// Subscriptions are only available over websockets.
     pub fn set_url(mut self, url: &str) -> Self {
         self.url = Some(url.to_string());
         self
     }
 
+    /// Re-open the websocket connection according to `policy` if the node goes away.
+    ///
+    /// Every HTTP request opens its own connection, so the policy is ignored for
+    /// `http://` urls.
+    ///
+    /// # Subscription gaps
+    ///
+    /// Subscriptions are re-issued on the new connection and report the interruption with
+    /// an `Err(Error::SubscriptionGap)` item, after which they carry on. Consumers of long
+    /// running subscriptions must handle that item instead of propagating it with `?`,
+    /// which would stop at the first reconnection.
+    pub fn set_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Set the default timeout for every rpc call made by the client.
+    ///
+    /// Can be overridden per call with `Client::with_timeout`.
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the default timeout for a submitted extrinsic to be included in a block.
+    ///
+    /// The timeout set with `set_timeout` only applies to the individual calls made while
+    /// waiting. Can be overridden per call with `Client::with_timeout`.
+    pub fn set_watch_timeout(mut self, timeout: Duration) -> Self {
+        self.watch_timeout = Some(timeout);
+        self
+    }
+
+    /// Use a custom transport, such as a `MockTransport`, instead of connecting to the url.
+    pub fn set_transport<R: Transport + 'static>(mut self, transport: R) -> Self {
+        self.rpc = Some(Rpc::new(transport));
+        self
+    }
+
+    /// Fail to build the client if the size of any event argument type in the metadata is
+    /// not registered, instead of failing once such an event is received.
+    pub fn set_strict_event_types(mut self, strict: bool) -> Self {
+        self.strict_event_types = strict;
+        self
+    }
+
     /// Creates a new Client.
     pub async fn build(self) -> Result<Client<T, S>, Error> {
-        let url = self.url.unwrap_or("ws://127.0.0.1:9944".to_string());
-        let rpc = Rpc::connect_ws(&url).await?;
+        let mut rpc = match self.rpc {
+            Some(rpc) => rpc,
+            None => {
+                let url = self.url.unwrap_or("ws://127.0.0.1:9944".to_string());
+                match (self.reconnect, url::Url::parse(&url)?.scheme()) {
+                    (Some(policy), "ws") | (Some(policy), "wss") => {
+                        Rpc::new(ReconnectingTransport::connect_ws(&url, policy).await?)
+                    }
+                    _ => Rpc::connect(&url).await?,
+                }
+            }
+        };
+        rpc.set_timeout(self.timeout);
+        rpc.set_watch_timeout(self.watch_timeout);
 
-        let (metadata, genesis_hash, runtime_version) = future::join3(
-            rpc.metadata(),
+        let (metadata_bytes, genesis_hash, runtime_version) = future::join3(
+            rpc.metadata_bytes(),
             rpc.genesis_hash(),
             rpc.runtime_version(None),
         )
         .await;
+        let metadata_bytes = metadata_bytes?;
+        let metadata = Metadata::decode(&metadata_bytes[..])?;
+        metadata.check_signed_extensions(
+            &<<T::SignedExtra as SignedExtra<T>>::Extra as SignedExtension>::identifier(),
+        )?;
+        if self.strict_event_types {
+            EventsDecoder::<T>::try_from(metadata.clone())?.ensure_type_sizes()?;
+        }
         Ok(Client {
             rpc,
             genesis_hash: genesis_hash?,
-            metadata: metadata?,
+            metadata,
+            metadata_bytes,
             runtime_version: runtime_version?,
             _marker: PhantomData,
         })
@@ -156,6 +300,7 @@ pub struct Client<T: System, S = MultiSignature> {
     rpc: Rpc<T>,
     genesis_hash: T::Hash,
     metadata: Metadata,
+    metadata_bytes: Bytes,
     runtime_version: RuntimeVersion,
     _marker: PhantomData<fn() -> S>,
 }
@@ -166,18 +311,47 @@ impl<T: System, S> Clone for Client<T, S> {
             rpc: self.rpc.clone(),
             genesis_hash: self.genesis_hash,
             metadata: self.metadata.clone(),
+            metadata_bytes: self.metadata_bytes.clone(),
             runtime_version: self.runtime_version.clone(),
             _marker: PhantomData,
         }
     }
 }
 
-impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
+impl<T: Runtime + Sync + Send + 'static, S: 'static> Client<T, S> {
     /// Returns the chain metadata.
     pub fn metadata(&self) -> &Metadata {
         &self.metadata
     }
 
+    /// Returns the chain metadata as encoded by the node.
+    ///
+    /// Cache the bytes, together with the `genesis` hash and `runtime_version`, to build
+    /// transactions offline: `Metadata::decode` restores the metadata which
+    /// `SigningPayload::new` takes, and the rest goes into the `DefaultExtra`.
+    pub fn metadata_bytes(&self) -> &[u8] {
+        &self.metadata_bytes
+    }
+
+    /// Returns the genesis hash of the chain.
+    pub fn genesis(&self) -> &T::Hash {
+        &self.genesis_hash
+    }
+
+    /// Returns the runtime version of the chain when the client was built.
+    pub fn runtime_version(&self) -> &RuntimeVersion {
+        &self.runtime_version
+    }
+
+    /// Returns a client whose calls, and waits for submitted extrinsics to be included in
+    /// a block, time out after `timeout`, overriding the defaults.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.rpc.set_timeout(Some(timeout));
+        client.rpc.set_watch_timeout(Some(timeout));
+        client
+    }
+
     /// Fetch a StorageKey.
     pub async fn fetch<V: Decode>(
         &self,
@@ -208,6 +382,151 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
         Ok(result.unwrap_or_default())
     }
 
+    /// Fetch a plain storage value of `module` or return its default.
+    pub async fn fetch_value<V: Decode + Clone>(
+        &self,
+        module: &str,
+        storage: &'static str,
+        hash: Option<T::Hash>,
+    ) -> Result<V, Error> {
+        let value = self
+            .metadata
+            .module(module)?
+            .storage(storage)?
+            .get_value::<V>()?;
+        self.fetch_or(value.key(), hash, value.default()).await
+    }
+
+    /// Fetch a key of a child trie.
+    pub async fn fetch_child<V: Decode>(
+        &self,
+        child: &ChildTrie,
+        key: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<V>, Error> {
+        match self.rpc.child_storage(child, key, hash).await? {
+            Some(data) => Ok(Some(Decode::decode(&mut &data.0[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch the keys of a child trie with the given prefix.
+    pub async fn child_keys(
+        &self,
+        child: &ChildTrie,
+        prefix: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, Error> {
+        self.rpc.child_storage_keys(child, prefix, hash).await
+    }
+
+    /// Fetch up to `count` keys with the given prefix, in order, starting after
+    /// `start_key`.
+    pub async fn storage_keys_paged(
+        &self,
+        prefix: Option<StorageKey>,
+        count: u32,
+        start_key: Option<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, Error> {
+        self.rpc
+            .storage_keys_paged(prefix, count, start_key, hash)
+            .await
+    }
+
+    /// Iterate over all entries of a storage map.
+    ///
+    /// The entries are read at block `hash`, or at the latest block if `None`. Keys are
+    /// fetched in pages of `STORAGE_KEYS_PAGE_SIZE` and the values of each page with a
+    /// single query. The map key is only recovered for maps with a concat hasher.
+    pub async fn iter_map<K, V>(
+        &self,
+        map: StorageMap<K, V>,
+        hash: Option<T::Hash>,
+    ) -> Result<impl Stream<Item = Result<(StorageKey, Option<K>, V), Error>>, Error>
+    where
+        K: Encode + Decode,
+        V: Decode + Clone,
+    {
+        // all pages are read at the same block so entries can't move between pages
+        let hash = match hash {
+            Some(hash) => hash,
+            None => {
+                self.block_hash(None)
+                    .await?
+                    .ok_or("Latest block hash not found")?
+            }
+        };
+        let rpc = self.rpc.clone();
+        let map = Arc::new(map);
+        let pages = stream::unfold(Some(None), move |start_key| {
+            let rpc = rpc.clone();
+            let map = map.clone();
+            async move {
+                let start_key = start_key?;
+                match map_page(&rpc, &map, start_key, hash).await {
+                    Ok(entries) => {
+                        let next = if entries.len() < STORAGE_KEYS_PAGE_SIZE as usize {
+                            None
+                        } else {
+                            entries.last().map(|(key, _, _)| Some(key.clone()))
+                        };
+                        Some((Ok(entries), next))
+                    }
+                    Err(err) => Some((Err(err), None)),
+                }
+            }
+        });
+        Ok(pages.flat_map(|page| {
+            let entries = match page {
+                Ok(entries) => entries.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            };
+            stream::iter(entries)
+        }))
+    }
+
+    /// Fetch a proof of the storage entries at `keys`.
+    pub async fn read_proof(
+        &self,
+        keys: Vec<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<ReadProof<T::Hash>, Error> {
+        self.rpc.read_proof(keys, hash).await
+    }
+
+    /// Fetch a StorageKey together with its proof, verified against the state root of
+    /// block `hash`, or of the latest finalized block if `None`.
+    ///
+    /// The header providing the state root is checked to hash to `hash`, so the value is
+    /// as trustworthy as the block hash.
+    pub async fn fetch_with_proof<V: Decode>(
+        &self,
+        key: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<(Option<V>, ReadProof<T::Hash>), Error> {
+        let hash = match hash {
+            Some(hash) => hash,
+            None => self.finalized_head().await?,
+        };
+        let header = self
+            .header(Some(hash))
+            .await?
+            .ok_or_else(|| Error::Other(format!("Header not found for {:?}", hash)))?;
+        if header.hash() != hash {
+            return Err(Error::InvalidProof(format!(
+                "Header does not match block {:?}",
+                hash
+            )))
+        }
+        let proof = self.read_proof(vec![key.clone()], Some(hash)).await?;
+        let value = match proof.verify::<T::Hashing>(header.state_root(), &key)? {
+            Some(data) => Some(Decode::decode(&mut &data[..])?),
+            None => None,
+        };
+        Ok((value, proof))
+    }
+
     /// Query historical storage entries
     pub async fn query_storage(
         &self,
@@ -260,6 +579,12 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
         Ok(xt_hash)
     }
 
+    /// Get the extrinsics waiting in the transaction pool
+    pub async fn pending_extrinsics(&self) -> Result<Vec<Bytes>, Error> {
+        let extrinsics = self.rpc.pending_extrinsics().await?;
+        Ok(extrinsics)
+    }
+
     /// Create and submit an extrinsic and return corresponding Event if successful
     pub async fn submit_and_watch_extrinsic<E: Encode + 'static>(
         self,
@@ -273,6 +598,19 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
         Ok(success)
     }
 
+    /// Fetch the events of the block with the given hash.
+    ///
+    /// Reads the `System::Events` storage at the block, so unlike `subscribe_events` the
+    /// events can be fetched at any time after the block is imported.
+    pub async fn events_at(
+        &self,
+        block_hash: T::Hash,
+    ) -> Result<Vec<EventRecord<T>>, Error> {
+        let decoder = EventsDecoder::<T>::try_from(self.metadata.clone())?;
+        let events = self.rpc.events_at(&decoder, block_hash).await?;
+        Ok(events)
+    }
+
     /// Subscribe to events.
     pub async fn subscribe_events(
         &self,
@@ -281,6 +619,32 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
         Ok(events)
     }
 
+    /// Subscribe to the events indexed by `topic`, together with the hash of the block
+    /// they were emitted in.
+    pub async fn subscribe_events_by_topic(
+        &self,
+        topic: T::Hash,
+    ) -> Result<impl Stream<Item = Result<(T::Hash, EventRecord<T>), Error>>, Error> {
+        self.subscribe_decoded_events()
+            .filter_topic(topic)
+            .subscribe()
+            .await
+    }
+
+    /// Create a subscription to the decoded events of new blocks.
+    pub fn subscribe_decoded_events(&self) -> EventSubscriptionBuilder<T, S> {
+        let decoder = EventsDecoder::try_from(self.metadata.clone()).map_err(Into::into);
+        EventSubscriptionBuilder {
+            client: self.clone(),
+            decoder,
+            filter: EventFilter {
+                events: Vec::new(),
+                topics: Vec::new(),
+            },
+            finalized: false,
+        }
+    }
+
     /// Subscribe to new blocks.
     pub async fn subscribe_blocks(&self) -> Result<Subscription<T::Header>, Error> {
         let headers = self.rpc.subscribe_blocks().await?;
@@ -295,19 +659,16 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
         Ok(headers)
     }
 
-    /// Create a transaction builder for a private key.
+    /// Create a transaction builder for a private key or an external signer.
     pub async fn xt<P>(
         &self,
         signer: P,
         nonce: Option<T::Index>,
     ) -> Result<XtBuilder<T, P, S>, Error>
     where
-        P: Pair,
-        P::Signature: Codec,
-        S: Verify,
-        S::Signer: From<P::Public> + IdentifyAccount<AccountId = T::AccountId>,
+        P: Signer<T, S>,
     {
-        let account_id = S::Signer::from(signer.public()).into_account();
+        let account_id = signer.account_id();
         let nonce = match nonce {
             Some(nonce) => nonce,
             None => self.account_nonce(account_id).await?,
@@ -320,6 +681,10 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
             nonce,
             runtime_version,
             genesis_hash,
+            era: Era::Immortal,
+            era_hash: genesis_hash,
+            tip: Default::default(),
+            tip_policy: None,
             signer,
         })
     }
@@ -327,23 +692,32 @@ impl<T: System + Balances + Sync + Send + 'static, S: 'static> Client<T, S> {
 
 /// Transaction builder.
 #[derive(Clone)]
-pub struct XtBuilder<T: System, P, S> {
+pub struct XtBuilder<T: Balances, P, S> {
     client: Client<T, S>,
     nonce: T::Index,
     runtime_version: RuntimeVersion,
     genesis_hash: T::Hash,
+    era: Era,
+    era_hash: T::Hash,
+    tip: T::Balance,
+    tip_policy: Option<Arc<dyn TipPolicy<T, S>>>,
     signer: P,
 }
 
-impl<T: System + Balances + Send + Sync + 'static, P, S: 'static> XtBuilder<T, P, S>
+impl<T: Runtime + Send + Sync + 'static, P, S: 'static> XtBuilder<T, P, S>
 where
-    P: Pair,
+    P: Signer<T, S>,
 {
     /// Returns the chain metadata.
     pub fn metadata(&self) -> &Metadata {
         self.client.metadata()
     }
 
+    /// Returns the signer.
+    pub fn signer(&self) -> &P {
+        &self.signer
+    }
+
     /// Returns the nonce.
     pub fn nonce(&self) -> T::Index {
         self.nonce
@@ -360,17 +734,75 @@ where
         self.set_nonce(self.nonce() + 1.into());
         self
     }
+
+    /// Returns the era transactions are valid for.
+    pub fn era(&self) -> Era {
+        self.era
+    }
+
+    /// Makes transactions valid for `period` blocks starting at the current best block,
+    /// instead of forever.
+    ///
+    /// The period is rounded to a power of two between 4 and 65536. The hash of the block
+    /// the era starts at is fetched, as it is signed along with the transaction.
+    pub async fn mortal(
+        &mut self,
+        period: u64,
+    ) -> Result<&mut XtBuilder<T, P, S>, Error> {
+        let header = self
+            .client
+            .header::<T::Hash>(None)
+            .await?
+            .ok_or_else(|| Error::Other("Failed to find the best block".into()))?;
+        let current: u64 = (*header.number()).unique_saturated_into();
+        let era = Era::mortal(period, current);
+        let birth = T::BlockNumber::unique_saturated_from(era.birth(current));
+        let era_hash = self
+            .client
+            .block_hash(Some(NumberOrHex::Number(birth)))
+            .await?
+            .ok_or_else(|| {
+                Error::Other(format!("Failed to find the hash of block {:?}", birth))
+            })?;
+        self.era = era;
+        self.era_hash = era_hash;
+        Ok(self)
+    }
+
+    /// Sets the tip paid to prioritise transactions.
+    pub fn tip(&mut self, tip: T::Balance) -> &mut XtBuilder<T, P, S> {
+        self.tip = tip;
+        self
+    }
+
+    /// Computes the tip with `policy` whenever a transaction is signed.
+    ///
+    /// The policy takes precedence over the tip set with `tip`.
+    pub fn tip_policy<Tp>(&mut self, policy: Tp) -> &mut XtBuilder<T, P, S>
+    where
+        Tp: TipPolicy<T, S> + 'static,
+    {
+        self.tip_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// The tip of a transaction signed now.
+    async fn signing_tip(&self) -> Result<T::Balance, Error> {
+        match &self.tip_policy {
+            Some(policy) => policy.tip(&self.client).await,
+            None => Ok(self.tip),
+        }
+    }
 }
 
-impl<T: System + Balances + Send + Sync + 'static, P, S: 'static> XtBuilder<T, P, S>
+impl<T: Runtime + Send + Sync + 'static, P, S: 'static> XtBuilder<T, P, S>
 where
-    P: Pair,
-    S: Verify + Codec + From<P::Signature>,
-    S::Signer: From<P::Public> + IdentifyAccount<AccountId = T::AccountId>,
+    P: Signer<T, S>,
+    S: Codec,
     T::Address: From<T::AccountId>,
 {
     /// Creates and signs an Extrinsic for the supplied `Call`
-    pub fn create_and_sign<C>(
+    pub async fn create_and_sign<C>(
         &self,
         call: Call<C>,
     ) -> Result<
@@ -378,14 +810,14 @@ where
             T::Address,
             Encoded,
             S,
-            <DefaultExtra<T> as SignedExtra<T>>::Extra,
+            <T::SignedExtra as SignedExtra<T>>::Extra,
         >,
         Error,
     >
     where
         C: codec::Encode,
     {
-        let signer = self.signer.clone();
+        let tip = self.signing_tip().await?;
         let account_nonce = self.nonce;
         let version = self.runtime_version.spec_version;
         let genesis_hash = self.genesis_hash;
@@ -400,14 +832,17 @@ where
             account_nonce
         );
 
-        let extra = extrinsic::DefaultExtra::new(version, account_nonce, genesis_hash);
-        let xt = extrinsic::create_and_sign::<_, _, _, S, _>(signer, call, extra)?;
+        let extra = T::SignedExtra::new(version, account_nonce, genesis_hash)
+            .with_era(self.era, self.era_hash)
+            .with_tip(tip);
+        let xt = extrinsic::create_and_sign::<_, _, _, S, _>(&self.signer, call, extra)
+            .await?;
         Ok(xt)
     }
 
     /// Submits a transaction to the chain.
     pub async fn submit<C: Encode>(&self, call: Call<C>) -> Result<T::Hash, Error> {
-        let extrinsic = self.create_and_sign(call)?;
+        let extrinsic = self.create_and_sign(call).await?;
         let xt_hash = self.client.submit_extrinsic(extrinsic).await?;
         Ok(xt_hash)
     }
@@ -425,18 +860,16 @@ where
 }
 
 /// Submits an extrinsic and subscribes to the triggered events
-pub struct EventsSubscriber<T: System, P, S> {
+pub struct EventsSubscriber<T: Balances, P, S> {
     client: Client<T, S>,
     builder: XtBuilder<T, P, S>,
     decoder: Result<EventsDecoder<T>, EventsError>,
 }
 
-impl<T: System + Balances + Send + Sync + 'static, P, S: 'static>
-    EventsSubscriber<T, P, S>
+impl<T: Runtime + Send + Sync + 'static, P, S: 'static> EventsSubscriber<T, P, S>
 where
-    P: Pair,
-    S: Verify + Codec + From<P::Signature>,
-    S::Signer: From<P::Public> + IdentifyAccount<AccountId = T::AccountId>,
+    P: Signer<T, S>,
+    S: Codec,
     T::Address: From<T::AccountId>,
 {
     /// Access the events decoder for registering custom type sizes
@@ -461,7 +894,7 @@ where
         call: Call<C>,
     ) -> Result<ExtrinsicSuccess<T>, Error> {
         let decoder = self.decoder?;
-        let extrinsic = self.builder.create_and_sign(call)?;
+        let extrinsic = self.builder.create_and_sign(call).await?;
         let xt_success = self
             .client
             .submit_and_watch_extrinsic(extrinsic, decoder)
@@ -470,6 +903,200 @@ where
     }
 }
 
+/// Selects the events yielded by an event subscription.
+struct EventFilter<T: System> {
+    events: Vec<(String, Option<String>)>,
+    topics: Vec<T::Hash>,
+}
+
+impl<T: System> EventFilter<T> {
+    fn matches(&self, record: &EventRecord<T>) -> bool {
+        let event_matches = self.events.is_empty()
+            || self.events.iter().any(|(module, variant)| {
+                record.event.module() == module
+                    && variant
+                        .as_ref()
+                        .map_or(true, |variant| record.event.variant() == variant)
+            });
+        let topic_matches = self.topics.is_empty()
+            || self
+                .topics
+                .iter()
+                .any(|topic| record.topics.contains(topic));
+        event_matches && topic_matches
+    }
+}
+
+/// Subscribes to the decoded events of new blocks
+///
+/// Without filters every event is yielded. Events are yielded if they match any of the
+/// module and event filters, and are indexed by any of the topic filters.
+pub struct EventSubscriptionBuilder<T: System, S> {
+    client: Client<T, S>,
+    decoder: Result<EventsDecoder<T>, EventsError>,
+    filter: EventFilter<T>,
+    finalized: bool,
+}
+
+impl<T: Runtime + Send + Sync + 'static, S: 'static> EventSubscriptionBuilder<T, S> {
+    /// Access the events decoder for registering custom type sizes
+    pub fn events_decoder<
+        F: FnOnce(&mut EventsDecoder<T>) -> Result<usize, EventsError>,
+    >(
+        self,
+        f: F,
+    ) -> Self {
+        let mut this = self;
+        if let Ok(ref mut decoder) = this.decoder {
+            if let Err(err) = f(decoder) {
+                this.decoder = Err(err)
+            }
+        }
+        this
+    }
+
+    /// Yield all events of the given module.
+    pub fn filter_module(mut self, module: &str) -> Self {
+        self.filter.events.push((module.to_string(), None));
+        self
+    }
+
+    /// Yield the events of the given module and variant.
+    pub fn filter_variant(mut self, module: &str, variant: &str) -> Self {
+        self.filter
+            .events
+            .push((module.to_string(), Some(variant.to_string())));
+        self
+    }
+
+    /// Yield the events decoding to `E`.
+    pub fn filter_event<E: Event>(self) -> Self {
+        self.filter_variant(E::MODULE, E::EVENT)
+    }
+
+    /// Yield the events indexed by `topic`.
+    pub fn filter_topic(mut self, topic: T::Hash) -> Self {
+        self.filter.topics.push(topic);
+        self
+    }
+
+    /// Follow finalized blocks instead of the best blocks.
+    ///
+    /// The events are fetched for each finalized head. Blocks finalized together with a
+    /// later head, or while reconnecting, are fetched by number first, so the events of
+    /// every finalized block are yielded in order.
+    pub fn finalized(mut self) -> Self {
+        self.finalized = true;
+        self
+    }
+
+    /// Subscribe to the events, together with the hash of the block they were emitted in.
+    pub async fn subscribe(
+        self,
+    ) -> Result<impl Stream<Item = Result<(T::Hash, EventRecord<T>), Error>>, Error> {
+        let decoder = self.decoder?;
+        let rpc = self.client.rpc.clone();
+        let blocks = if self.finalized {
+            let headers = rpc.subscribe_finalized_blocks().await?;
+            future::Either::Left(stream::unfold(
+                (headers, rpc, decoder, None),
+                |(mut headers, rpc, decoder, mut last)| {
+                    async move {
+                        loop {
+                            let events = match headers.next().await {
+                                // e.g. the current head, announced again on resubscribing
+                                Some(Ok(header))
+                                    if last.map_or(false, |last| {
+                                        *header.number() <= last
+                                    }) =>
+                                {
+                                    continue
+                                }
+                                Some(Ok(header)) => {
+                                    let events =
+                                        finalized_events(&rpc, &decoder, last, &header)
+                                            .await;
+                                    if events.is_ok() {
+                                        last = Some(*header.number());
+                                    }
+                                    events
+                                }
+                                // the blocks missed in between are fetched with the next head
+                                Some(Err(Error::SubscriptionGap)) => continue,
+                                Some(Err(err)) => Err(err),
+                                None => return None,
+                            };
+                            return Some((events, (headers, rpc, decoder, last)))
+                        }
+                    }
+                },
+            ))
+        } else {
+            let change_sets = rpc.subscribe_events().await?;
+            future::Either::Right(stream::unfold(
+                (change_sets, decoder),
+                |(mut change_sets, decoder)| {
+                    async move {
+                        let events = match change_sets.next().await {
+                            Some(Ok(change_set)) => {
+                                change_set_events(&decoder, change_set)
+                            }
+                            Some(Err(err)) => Err(err),
+                            None => return None,
+                        };
+                        Some((events, (change_sets, decoder)))
+                    }
+                },
+            ))
+        };
+        let filter = self.filter;
+        Ok(blocks.flat_map(move |events| {
+            let events = match events {
+                Ok(events) => {
+                    events
+                        .into_iter()
+                        .filter(|(_, record)| filter.matches(record))
+                        .map(Ok)
+                        .collect::<Vec<_>>()
+                }
+                Err(err) => vec![Err(err)],
+            };
+            stream::iter(events)
+        }))
+    }
+}
+
+/// Fetches the events of the finalized `header`, preceded by those of the blocks finalized
+/// since the head with the number `last`.
+async fn finalized_events<T: Runtime + Send + Sync + 'static>(
+    rpc: &Rpc<T>,
+    decoder: &EventsDecoder<T>,
+    last: Option<T::BlockNumber>,
+    header: &T::Header,
+) -> Result<Vec<(T::Hash, EventRecord<T>)>, Error> {
+    let mut hashes = Vec::new();
+    if let Some(last) = last {
+        let mut number = last + One::one();
+        while number < *header.number() {
+            let hash = rpc
+                .block_hash(Some(NumberOrHex::Number(number)))
+                .await?
+                .ok_or_else(|| {
+                    Error::Other(format!("Failed to find the hash of block {:?}", number))
+                })?;
+            hashes.push(hash);
+            number += One::one();
+        }
+    }
+    hashes.push(header.hash());
+    let mut events = Vec::new();
+    for hash in hashes {
+        let records = rpc.events_at(decoder, hash).await?;
+        events.extend(records.into_iter().map(|record| (hash, record)));
+    }
+    Ok(events)
+}
+
 /// Wraps an already encoded byte vector, prevents being encoded as a raw byte vector as part of
 /// the transaction payload
 #[derive(Clone)]
@@ -481,18 +1108,97 @@ impl codec::Encode for Encoded {
     }
 }
 
+/// Decodes the event records of a change set of the `System::Events` storage, together
+/// with the hash of the block they were emitted in.
+fn change_set_events<T: System + Balances + 'static>(
+    decoder: &EventsDecoder<T>,
+    change_set: StorageChangeSet<T::Hash>,
+) -> Result<Vec<(T::Hash, EventRecord<T>)>, Error> {
+    let block = change_set.block;
+    let mut events = Vec::new();
+    for (_key, data) in change_set.changes {
+        if let Some(data) = data {
+            let records = decoder.decode_events(&mut &data.0[..])?;
+            events.extend(records.into_iter().map(|record| (block, record)));
+        }
+    }
+    Ok(events)
+}
+
+/// Fetches a page of map entries starting after `start_key`.
+async fn map_page<T, K, V>(
+    rpc: &Rpc<T>,
+    map: &StorageMap<K, V>,
+    start_key: Option<StorageKey>,
+    hash: T::Hash,
+) -> Result<Vec<(StorageKey, Option<K>, V)>, Error>
+where
+    T: System + Balances + 'static,
+    K: Encode + Decode,
+    V: Decode + Clone,
+{
+    let keys = rpc
+        .storage_keys_paged(
+            Some(map.prefix()),
+            STORAGE_KEYS_PAGE_SIZE,
+            start_key,
+            Some(hash),
+        )
+        .await?;
+    if keys.is_empty() {
+        return Ok(Vec::new())
+    }
+    let mut values: HashMap<StorageKey, Option<StorageData>> = HashMap::new();
+    for change_set in rpc.query_storage(keys.clone(), hash, Some(hash)).await? {
+        values.extend(change_set.changes);
+    }
+    let mut entries = Vec::with_capacity(keys.len());
+    for key in keys {
+        let value = match values.remove(&key) {
+            Some(Some(data)) => Decode::decode(&mut &data.0[..])?,
+            _ => map.default(),
+        };
+        let map_key = map.decode_key(&key);
+        entries.push((key, map_key, value));
+    }
+    Ok(entries)
+}
+
 #[cfg(test)]
 mod tests {
 
-    use sp_keyring::AccountKeyring;
+    use codec::Compact;
+    use frame_support::weights::DispatchInfo;
+    use sp_core::{
+        storage::StorageData,
+        twox_128,
+        Bytes,
+        Pair,
+        H256,
+    };
+    use sp_keyring::{
+        AccountKeyring,
+        Ed25519Keyring,
+    };
+    use sp_runtime::{
+        generic::Block,
+        traits::Header,
+        DispatchError,
+        OpaqueExtrinsic,
+    };
+    use sp_transaction_pool::TransactionStatus;
 
     use super::*;
     use crate::{
         frame::balances::BalancesStore,
+        metadata::fixtures,
         DefaultNodeRuntime as Runtime,
         Error,
     };
 
+    type AccountId = <Runtime as System>::AccountId;
+    type Balance = <Runtime as Balances>::Balance;
+
     pub(crate) async fn test_client() -> Client<Runtime> {
         ClientBuilder::<Runtime>::new()
             .build()
@@ -500,6 +1206,112 @@ mod tests {
             .expect("Error creating client")
     }
 
+    pub(crate) fn genesis_hash() -> H256 {
+        H256::repeat_byte(1)
+    }
+
+    pub(crate) fn runtime_version() -> RuntimeVersion {
+        RuntimeVersion {
+            spec_name: "node".into(),
+            impl_name: "substrate-node".into(),
+            authoring_version: 10,
+            spec_version: 1,
+            impl_version: 1,
+            apis: Default::default(),
+        }
+    }
+
+    /// Creates a client on top of `transport`, scripting the calls made while building it.
+    pub(crate) async fn mock_client(transport: &MockTransport) -> Client<Runtime> {
+        mock_client_with_metadata(transport, fixtures::node_metadata_bytes()).await
+    }
+
+    /// Creates a client on top of `transport` for a runtime with the given encoded metadata.
+    pub(crate) async fn mock_client_with_metadata(
+        transport: &MockTransport,
+        metadata: Vec<u8>,
+    ) -> Client<Runtime> {
+        transport
+            .respond("state_getMetadata", Bytes(metadata))
+            .respond("chain_getBlockHash", genesis_hash())
+            .respond("state_getRuntimeVersion", runtime_version());
+        ClientBuilder::<Runtime>::new()
+            .set_transport(transport.clone())
+            .build()
+            .await
+            .expect("Error creating mock client")
+    }
+
+    /// Encodes the `EventRecord` of an event emitted by the first extrinsic of a block.
+    pub(crate) fn event_record<E: Encode>(module_index: u8, event: E) -> Vec<u8> {
+        event_record_with_topics(module_index, event, vec![])
+    }
+
+    /// Encodes the `EventRecord` of an event indexed by `topics`, emitted by the first
+    /// extrinsic of a block.
+    pub(crate) fn event_record_with_topics<E: Encode>(
+        module_index: u8,
+        event: E,
+        topics: Vec<H256>,
+    ) -> Vec<u8> {
+        // `Phase::ApplyExtrinsic(0)`, followed by the event and its topics
+        (0u8, 0u32, module_index, event, topics).encode()
+    }
+
+    /// Value of the `System::Events` storage for the given encoded records.
+    pub(crate) fn events_storage(records: Vec<Vec<u8>>) -> StorageData {
+        let mut events = Compact(records.len() as u32).encode();
+        for record in records {
+            events.extend(record);
+        }
+        StorageData(events)
+    }
+
+    /// Change set of the `System::Events` storage for the given encoded records.
+    pub(crate) fn events_change_set(
+        block: H256,
+        records: Vec<Vec<u8>>,
+    ) -> StorageChangeSet<H256> {
+        let key = [twox_128(b"System"), twox_128(b"Events")].concat();
+        StorageChangeSet {
+            block,
+            changes: vec![(StorageKey(key), Some(events_storage(records)))],
+        }
+    }
+
+    /// Indices of `module` and its `event` in the `EventRecord`s of the runtime.
+    fn event_index(metadata: &Metadata, module: &str, event: &str) -> (u8, u8) {
+        let module = metadata
+            .modules_with_events()
+            .find(|m| m.name() == module)
+            .expect("Module should have events");
+        let event = (0..=u8::max_value())
+            .find(|&i| module.event(i).map(|e| e.name == event).unwrap_or(false))
+            .expect("Event should exist");
+        (module.index(), event)
+    }
+
+    /// Block at height 1 containing the given extrinsics.
+    pub(crate) fn block_with<E: Encode>(extrinsics: Vec<E>) -> ChainBlock<Runtime> {
+        let extrinsics = extrinsics
+            .iter()
+            .map(|xt| OpaqueExtrinsic::decode(&mut &xt.encode()[..]).unwrap())
+            .collect();
+        ChainBlock::<Runtime> {
+            block: Block {
+                header: <Runtime as System>::Header::new(
+                    1,
+                    Default::default(),
+                    Default::default(),
+                    genesis_hash(),
+                    Default::default(),
+                ),
+                extrinsics,
+            },
+            justification: None,
+        }
+    }
+
     #[test]
     #[ignore] // requires locally running substrate node
     fn test_tx_transfer_balance() {
@@ -586,4 +1398,765 @@ mod tests {
 
         assert!(result.is_ok())
     }
+
+    #[test]
+    fn test_with_timeout_overrides_default() {
+        let transport = MockTransport::new();
+        let client = async_std::task::block_on(mock_client(&transport));
+        let timeout = Duration::from_secs(5);
+
+        assert_eq!(client.rpc.timeout(), None);
+        assert_eq!(client.rpc.watch_timeout(), None);
+        let client = client.with_timeout(timeout);
+        assert_eq!(client.rpc.timeout(), Some(timeout));
+        assert_eq!(client.rpc.watch_timeout(), Some(timeout));
+    }
+
+    #[test]
+    fn test_mock_account_nonce() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport.respond("state_getStorage", StorageData(5u32.encode()));
+            let xt = client.xt(AccountKeyring::Alice.pair(), None).await?;
+            Ok(xt.nonce())
+        });
+
+        assert_eq!(result.unwrap(), 5);
+        assert!(transport
+            .requests()
+            .iter()
+            .any(|(method, _)| method == "state_getStorage"));
+    }
+
+    #[test]
+    fn test_mock_free_balance() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport.respond("state_getStorage", StorageData(1_000u128.encode()));
+            let account = AccountKeyring::Alice.to_account_id();
+            client.free_balance(account).await
+        });
+
+        assert_eq!(result.unwrap(), 1_000);
+    }
+
+    #[test]
+    fn test_mock_free_balance_default() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport.respond("state_getStorage", Option::<StorageData>::None);
+            let account = AccountKeyring::Alice.to_account_id();
+            client.free_balance(account).await
+        });
+
+        assert_eq!(result.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_mock_node_runtime_metadata() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client_with_metadata(
+                &transport,
+                fixtures::node_runtime_metadata_bytes(),
+            )
+            .await;
+            let signer = Ed25519Keyring::Alice.pair();
+            let from: AccountId = Ed25519Keyring::Alice.to_account_id().into();
+            let dest = AccountKeyring::Bob.to_account_id();
+            let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+            let xt = client.xt(signer, Some(0)).await?;
+            let extrinsic = xt.create_and_sign(transfer()).await?;
+
+            let (system, success) =
+                event_index(client.metadata(), "System", "ExtrinsicSuccess");
+            let (balances, transferred) =
+                event_index(client.metadata(), "Balances", "Transfer");
+            let records = vec![
+                event_record(system, (success, DispatchInfo::default())),
+                event_record(
+                    balances,
+                    (transferred, (from, dest.clone(), 10_000u128, 1u128)),
+                ),
+            ];
+            transport
+                .respond("state_getStorage", StorageData(1_000u128.encode()))
+                .respond("state_getStorage", events_storage(records))
+                .respond("chain_getBlock", block_with(vec![extrinsic]))
+                .notify(
+                    "author_submitAndWatchExtrinsic",
+                    vec![TransactionStatus::<H256, H256>::InBlock(H256::repeat_byte(
+                        2,
+                    ))],
+                );
+            let balance = client
+                .free_balance(AccountKeyring::Alice.to_account_id())
+                .await?;
+            let success = xt.watch().submit(transfer()).await?;
+            Ok((balance, success))
+        });
+
+        let (balance, success) = result.expect("Extrinsic should succeed");
+        assert_eq!(balance, 1_000);
+        let event = success
+            .find_event::<balances::TransferEvent<Runtime>>()
+            .expect("Transfer event should be found")
+            .expect("Transfer event should decode");
+        assert_eq!(event.to, AccountKeyring::Bob.to_account_id());
+        assert_eq!(event.amount, 10_000);
+    }
+
+    #[test]
+    fn test_mock_offline_signing_from_cached_metadata() {
+        let transport = MockTransport::new();
+        let signer = Ed25519Keyring::Alice.pair();
+        let dest = AccountKeyring::Bob.to_account_id();
+        let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let cache = (
+                client.metadata_bytes().to_vec(),
+                *client.genesis(),
+                client.runtime_version().spec_version,
+            );
+            let xt = client.xt(signer.clone(), Some(0)).await?;
+            let extrinsic = xt.create_and_sign(transfer()).await?;
+            Ok((cache, extrinsic))
+        });
+        let ((metadata_bytes, genesis, spec_version), extrinsic) =
+            result.expect("Extrinsic should be signed");
+        assert_eq!(metadata_bytes, fixtures::node_metadata_bytes());
+
+        let metadata = Metadata::decode(&metadata_bytes).unwrap();
+        let extra = DefaultExtra::<Runtime>::new(spec_version, 0, genesis);
+        let payload = SigningPayload::new(&metadata, transfer(), extra).unwrap();
+        let signature = MultiSignature::from(Pair::sign(&signer, &payload.to_bytes()));
+        let offline =
+            payload.into_extrinsic(Ed25519Keyring::Alice.to_account_id(), signature);
+        assert_eq!(offline.encode(), extrinsic.encode());
+    }
+
+    #[test]
+    fn test_mock_fetch_value() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport
+                .respond("state_getStorage", StorageData(1_000_000u128.encode()))
+                .respond("state_getStorage", Option::<StorageData>::None);
+            let issuance = client
+                .fetch_value::<u128>("Balances", "TotalIssuance", None)
+                .await?;
+            let number = client.fetch_value::<u32>("System", "Number", None).await?;
+            Ok((issuance, number))
+        });
+
+        assert_eq!(result.unwrap(), (1_000_000, 0));
+        let (method, params) = transport.requests().pop().unwrap();
+        assert_eq!(method, "state_getStorage");
+        let key = format!(
+            "0x{}",
+            hex::encode(
+                [sp_core::twox_128(b"System"), sp_core::twox_128(b"Number")].concat()
+            )
+        );
+        assert_eq!(params[0], key);
+    }
+
+    #[test]
+    fn test_mock_iter_map() {
+        use futures::TryStreamExt;
+
+        let transport = MockTransport::new();
+        let block_hash = H256::repeat_byte(2);
+        let result: Result<(Vec<_>, StorageKey), Error> =
+            async_std::task::block_on(async {
+                let client = mock_client(&transport).await;
+                let map = client
+                    .metadata()
+                    .module("Balances")?
+                    .storage("FreeBalance")?
+                    .get_map::<AccountId, Balance>()?;
+                // one full page and one partial page
+                let entries = (0..=STORAGE_KEYS_PAGE_SIZE)
+                    .map(|i| {
+                        let account = AccountId::from([i as u8; 32]);
+                        (map.key(account), StorageData((i as Balance).encode()))
+                    })
+                    .collect::<Vec<_>>();
+                let (first, second) = entries.split_at(STORAGE_KEYS_PAGE_SIZE as usize);
+                for page in &[first, second] {
+                    let keys =
+                        page.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+                    let changes = page
+                        .iter()
+                        .map(|(key, data)| (key.clone(), Some(data.clone())))
+                        .collect();
+                    transport.respond("state_getKeysPaged", keys).respond(
+                        "state_queryStorage",
+                        vec![StorageChangeSet {
+                            block: block_hash,
+                            changes,
+                        }],
+                    );
+                }
+                let last_key = first.last().unwrap().0.clone();
+                let entries = client
+                    .iter_map(map, Some(block_hash))
+                    .await?
+                    .try_collect()
+                    .await?;
+                Ok((entries, last_key))
+            });
+
+        let (entries, last_key) = result.unwrap();
+        assert_eq!(entries.len(), STORAGE_KEYS_PAGE_SIZE as usize + 1);
+        for (i, (_, key, value)) in entries.into_iter().enumerate() {
+            // `FreeBalance` is hashed with `Blake2_256` so the account can't be recovered
+            assert_eq!(key, None);
+            assert_eq!(value, i as Balance);
+        }
+        let pages = transport
+            .requests()
+            .into_iter()
+            .filter(|(method, _)| method == "state_getKeysPaged")
+            .map(|(_, params)| params)
+            .collect::<Vec<_>>();
+        assert_eq!(pages.len(), 2);
+        // the second page starts after the last key of the first
+        assert_eq!(pages[0][2], serde_json::Value::Null);
+        assert_eq!(pages[1][2], serde_json::to_value(last_key).unwrap());
+    }
+
+    #[test]
+    fn test_mock_fetch_with_proof() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let map = client
+                .metadata()
+                .module("Balances")?
+                .storage("FreeBalance")?
+                .get_map::<AccountId, Balance>()?;
+            let key = map.key(AccountKeyring::Alice.to_account_id());
+            let (state_root, proof) =
+                crate::rpc::tests::trie_proof(&[(&key.0[..], &1_000u128.encode()[..])]);
+            let header = <Runtime as System>::Header::new(
+                1,
+                Default::default(),
+                state_root,
+                genesis_hash(),
+                Default::default(),
+            );
+            let hash = header.hash();
+            transport
+                .respond("chain_getHeader", header)
+                .respond("state_getReadProof", ReadProof { at: hash, proof });
+
+            let (value, _) = client
+                .fetch_with_proof::<Balance>(key.clone(), Some(hash))
+                .await?;
+            // a header which doesn't hash to the requested block is rejected
+            let mismatch = client
+                .fetch_with_proof::<Balance>(key, Some(H256::repeat_byte(9)))
+                .await;
+            Ok((value, mismatch))
+        });
+
+        let (value, mismatch) = result.unwrap();
+        assert_eq!(value, Some(1_000));
+        assert!(match mismatch {
+            Err(Error::InvalidProof(_)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_mock_submit_and_watch_extrinsic() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            // ed25519 signatures are deterministic, so the submitted extrinsic can be
+            // signed upfront to script the block it is included in
+            let signer = Ed25519Keyring::Alice.pair();
+            let from: AccountId = Ed25519Keyring::Alice.to_account_id().into();
+            let dest = AccountKeyring::Bob.to_account_id();
+            let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+            let xt = client.xt(signer, Some(0)).await?;
+            let extrinsic = xt.create_and_sign(transfer()).await?;
+
+            let block_hash = H256::repeat_byte(2);
+            let records = vec![
+                event_record(0, (0u8, DispatchInfo::default())),
+                event_record(1, (0u8, (from, dest.clone(), 10_000u128, 1u128))),
+            ];
+            transport
+                .respond("chain_getBlock", block_with(vec![extrinsic]))
+                .respond("state_getStorage", events_storage(records))
+                .notify(
+                    "author_submitAndWatchExtrinsic",
+                    vec![
+                        TransactionStatus::<H256, H256>::Ready,
+                        TransactionStatus::InBlock(block_hash),
+                    ],
+                );
+            xt.watch().submit(transfer()).await
+        });
+
+        let success = result.expect("Extrinsic should succeed");
+        assert_eq!(success.block, H256::repeat_byte(2));
+        // the events are read from the block storage rather than a subscription
+        let events_request = transport
+            .requests()
+            .into_iter()
+            .find(|(method, _)| method == "state_getStorage")
+            .map(|(_, params)| params)
+            .expect("Events should be fetched");
+        assert_eq!(
+            events_request[1],
+            serde_json::to_value(success.block).unwrap()
+        );
+        assert_eq!(success.system_events().len(), 1);
+        let event = success
+            .find_event::<balances::TransferEvent<Runtime>>()
+            .expect("Transfer event should be found")
+            .expect("Transfer event should decode");
+        assert_eq!(event.to, AccountKeyring::Bob.to_account_id());
+        assert_eq!(event.amount, 10_000);
+        assert_eq!(
+            (
+                balances::TransferEvent::<Runtime>::MODULE,
+                balances::TransferEvent::<Runtime>::EVENT,
+            ),
+            ("Balances", "Transfer")
+        );
+    }
+
+    #[test]
+    fn test_mock_submit_with_tip_policy() {
+        let transport = MockTransport::new();
+        let signer = Ed25519Keyring::Alice.pair();
+        let dest = AccountKeyring::Bob.to_account_id();
+        let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport
+                .respond("author_pendingExtrinsics", vec![Bytes(vec![0]); 3])
+                .respond("author_submitExtrinsic", H256::repeat_byte(5));
+            let mut xt = client.xt(signer.clone(), Some(0)).await?;
+            xt.tip(1)
+                .tip_policy(CongestionTip::<Runtime>::new(10, 5, 100));
+            xt.submit(transfer()).await?;
+            let call = xt
+                .metadata()
+                .module_with_calls("Balances")
+                .and_then(|module| module.call("transfer", transfer().args))?;
+            Ok((xt.create_and_sign(transfer()).await?, call))
+        });
+
+        let (signed, call) = result.expect("Extrinsic should be submitted");
+        let extra = |tip| {
+            DefaultExtra::<Runtime>::new(
+                runtime_version().spec_version,
+                0,
+                genesis_hash(),
+            )
+            .with_tip(tip)
+        };
+        let sign = |tip| {
+            let signed = extrinsic::create_and_sign::<Runtime, _, _, MultiSignature, _>(
+                &signer,
+                call.clone(),
+                extra(tip),
+            );
+            async_std::task::block_on(signed).unwrap().encode()
+        };
+        // the policy tips 10 + 3 * 5 instead of the fixed tip, whether the extrinsic is
+        // submitted or only signed
+        let (_, params) = transport
+            .requests()
+            .into_iter()
+            .find(|(method, _)| method == "author_submitExtrinsic")
+            .unwrap();
+        assert_eq!(params[0], serde_json::to_value(Bytes(sign(25))).unwrap());
+        assert_eq!(signed.encode(), sign(25));
+    }
+
+    #[test]
+    fn test_mock_mortal_extrinsic() {
+        let transport = MockTransport::new();
+        let era_hash = H256::repeat_byte(9);
+        let best = <Runtime as System>::Header::new(
+            100,
+            Default::default(),
+            Default::default(),
+            genesis_hash(),
+            Default::default(),
+        );
+        // the era hash is queued after the genesis hash fetched by the builder
+        transport
+            .respond("state_getMetadata", Bytes(fixtures::node_metadata_bytes()))
+            .respond("chain_getBlockHash", genesis_hash())
+            .respond("chain_getBlockHash", era_hash)
+            .respond("state_getRuntimeVersion", runtime_version())
+            .respond("chain_getHeader", best);
+        let signer = Ed25519Keyring::Alice.pair();
+        let dest = AccountKeyring::Bob.to_account_id();
+        let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = ClientBuilder::<Runtime>::new()
+                .set_transport(transport.clone())
+                .build()
+                .await?;
+            let mut xt = client.xt(signer.clone(), Some(0)).await?;
+            xt.mortal(64).await?;
+            let call = xt
+                .metadata()
+                .module_with_calls("Balances")
+                .and_then(|module| module.call("transfer", transfer().args))?;
+            Ok((xt.era(), xt.create_and_sign(transfer()).await?, call))
+        });
+
+        let (era, extrinsic, call) = result.expect("Extrinsic should be signed");
+        assert_eq!(era, Era::mortal(64, 100));
+        let extra = DefaultExtra::<Runtime>::new(
+            runtime_version().spec_version,
+            0,
+            genesis_hash(),
+        )
+        .with_era(era, era_hash);
+        let expected = async_std::task::block_on(extrinsic::create_and_sign::<
+            Runtime,
+            _,
+            _,
+            MultiSignature,
+            _,
+        >(&signer, call, extra))
+        .unwrap();
+        assert_eq!(extrinsic.encode(), expected.encode());
+        let (_, params) = transport
+            .requests()
+            .into_iter()
+            .filter(|(method, _)| method == "chain_getBlockHash")
+            .last()
+            .unwrap();
+        assert_eq!(params, serde_json::json!([100]));
+    }
+
+    #[test]
+    fn test_mock_submit_failed_with_module_error() {
+        let transport = MockTransport::new();
+        let result: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let signer = Ed25519Keyring::Alice.pair();
+            let dest = AccountKeyring::Bob.to_account_id();
+            let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+            let xt = client.xt(signer, Some(0)).await?;
+            let extrinsic = xt.create_and_sign(transfer()).await?;
+
+            // `System::ExtrinsicFailed` with `Balances::InsufficientBalance`
+            let failed = DispatchError::Module {
+                index: 1,
+                error: 0,
+                message: None,
+            };
+            let records = vec![event_record(0, (1u8, failed, DispatchInfo::default()))];
+            transport
+                .respond("chain_getBlock", block_with(vec![extrinsic]))
+                .respond("state_getStorage", events_storage(records))
+                .notify(
+                    "author_submitAndWatchExtrinsic",
+                    vec![TransactionStatus::<H256, H256>::InBlock(H256::repeat_byte(
+                        2,
+                    ))],
+                );
+            xt.watch().submit(transfer()).await
+        });
+
+        match result {
+            Err(Error::Runtime {
+                module,
+                error,
+                docs,
+            }) => {
+                assert_eq!(module, "Balances");
+                assert_eq!(error, "InsufficientBalance");
+                assert_eq!(docs, vec![" Balance too low to send value".to_string()]);
+            }
+            other => panic!("Expected a runtime error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_mock_events_at() {
+        let transport = MockTransport::new();
+        let events: Result<_, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport.respond(
+                "state_getStorage",
+                events_storage(vec![event_record(0, (0u8, DispatchInfo::default()))]),
+            );
+            let events = client.events_at(H256::repeat_byte(2)).await?;
+            transport.respond("state_getStorage", Option::<StorageData>::None);
+            let empty = client.events_at(H256::repeat_byte(3)).await?;
+            Ok((events, empty))
+        });
+
+        let (events, empty) = events.expect("Events should be fetched");
+        assert_eq!(events.len(), 1);
+        match events[0] {
+            EventRecord {
+                phase: Phase::ApplyExtrinsic(0),
+                event: RuntimeEvent::System(_),
+                ..
+            } => (),
+            ref record => panic!("Unexpected event {:?}", record),
+        }
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_mock_subscribe_events_by_topic() {
+        let transport = MockTransport::new();
+        let did = H256::repeat_byte(7);
+        let alice = AccountKeyring::Alice.to_account_id();
+        let bob = AccountKeyring::Bob.to_account_id();
+        let transfer = (0u8, (alice, bob, 10_000u128, 1u128));
+        transport.notify(
+            "state_subscribeStorage",
+            vec![
+                events_change_set(
+                    H256::repeat_byte(2),
+                    vec![
+                        event_record(0, (0u8, DispatchInfo::default())),
+                        event_record_with_topics(1, transfer.clone(), vec![did]),
+                    ],
+                ),
+                events_change_set(
+                    H256::repeat_byte(3),
+                    vec![event_record_with_topics(
+                        1,
+                        transfer,
+                        vec![H256::repeat_byte(8)],
+                    )],
+                ),
+            ],
+        );
+        let events: Result<Vec<_>, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let events = client.subscribe_events_by_topic(did).await?;
+            events.collect::<Vec<_>>().await.into_iter().collect()
+        });
+
+        let events = events.expect("Events should be received");
+        assert_eq!(events.len(), 1);
+        let (block, record) = &events[0];
+        assert_eq!(*block, H256::repeat_byte(2));
+        assert_eq!(record.phase, Phase::ApplyExtrinsic(0));
+        assert_eq!(record.topics, vec![did]);
+    }
+
+    #[test]
+    fn test_mock_subscribe_decoded_events() {
+        let transport = MockTransport::new();
+        let alice = AccountKeyring::Alice.to_account_id();
+        let bob = AccountKeyring::Bob.to_account_id();
+        transport.notify(
+            "state_subscribeStorage",
+            vec![events_change_set(
+                H256::repeat_byte(2),
+                vec![
+                    event_record(0, (0u8, DispatchInfo::default())),
+                    event_record(1, (0u8, (alice, bob.clone(), 10_000u128, 1u128))),
+                ],
+            )],
+        );
+        let events: Result<Vec<_>, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let events = client
+                .subscribe_decoded_events()
+                .filter_event::<balances::TransferEvent<Runtime>>()
+                .subscribe()
+                .await?;
+            events.collect::<Vec<_>>().await.into_iter().collect()
+        });
+
+        let events = events.expect("Events should be received");
+        assert_eq!(events.len(), 1);
+        let (block, record) = &events[0];
+        assert_eq!(*block, H256::repeat_byte(2));
+        let transfer = match &record.event {
+            RuntimeEvent::Raw(raw) => {
+                balances::TransferEvent::<Runtime>::decode(&mut &raw.data[..])
+                    .expect("Transfer event should decode")
+            }
+            RuntimeEvent::System(_) => panic!("Expected a raw event"),
+        };
+        assert_eq!(transfer.to, bob);
+    }
+
+    #[test]
+    fn test_mock_subscribe_finalized_events() {
+        let transport = MockTransport::new();
+        let header = <Runtime as System>::Header::new(
+            1,
+            Default::default(),
+            Default::default(),
+            genesis_hash(),
+            Default::default(),
+        );
+        transport
+            .notify("chain_subscribeFinalizedHeads", vec![header.clone()])
+            .respond(
+                "state_getStorage",
+                events_storage(vec![event_record(0, (0u8, DispatchInfo::default()))]),
+            );
+        let events: Result<Vec<_>, Error> = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let events = client
+                .subscribe_decoded_events()
+                .filter_module("System")
+                .finalized()
+                .subscribe()
+                .await?;
+            events.collect::<Vec<_>>().await.into_iter().collect()
+        });
+
+        let events = events.expect("Events should be received");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, header.hash());
+        assert_eq!(events[0].1.event.variant(), "ExtrinsicSuccess");
+    }
+
+    #[test]
+    fn test_mock_subscribe_finalized_events_backfills() {
+        let transport = MockTransport::new();
+        let header = |number| {
+            <Runtime as System>::Header::new(
+                number,
+                Default::default(),
+                Default::default(),
+                genesis_hash(),
+                Default::default(),
+            )
+        };
+        // blocks 2 and 3 are finalized together with block 4
+        let (first, last) = (header(1), header(4));
+        let skipped = vec![H256::repeat_byte(2), H256::repeat_byte(3)];
+        transport
+            .respond("state_getMetadata", Bytes(fixtures::node_metadata_bytes()))
+            .respond("chain_getBlockHash", genesis_hash())
+            .respond("chain_getBlockHash", skipped[0])
+            .respond("chain_getBlockHash", skipped[1])
+            .respond("state_getRuntimeVersion", runtime_version())
+            .respond(
+                "state_getStorage",
+                events_storage(vec![event_record(0, (0u8, DispatchInfo::default()))]),
+            )
+            .notify(
+                "chain_subscribeFinalizedHeads",
+                vec![first.clone(), last.clone()],
+            );
+        let events: Result<Vec<_>, Error> = async_std::task::block_on(async {
+            let client = ClientBuilder::<Runtime>::new()
+                .set_transport(transport.clone())
+                .build()
+                .await?;
+            let events = client
+                .subscribe_decoded_events()
+                .finalized()
+                .subscribe()
+                .await?;
+            events.collect::<Vec<_>>().await.into_iter().collect()
+        });
+
+        let blocks = events
+            .expect("Events should be received")
+            .into_iter()
+            .map(|(block, _)| block)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            blocks,
+            vec![first.hash(), skipped[0], skipped[1], last.hash()]
+        );
+        let numbers = transport
+            .requests()
+            .into_iter()
+            .filter(|(method, _)| method == "chain_getBlockHash")
+            .map(|(_, params)| params[0].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers[1..], [serde_json::json!(2), serde_json::json!(3)]);
+    }
+
+    #[test]
+    fn test_mock_strict_event_types() {
+        let mut modules = fixtures::node_modules();
+        modules.push(fixtures::event_module(
+            "Custom",
+            vec![fixtures::event("Happened", &["CustomId"])],
+        ));
+        let transport = MockTransport::new();
+        transport
+            .respond(
+                "state_getMetadata",
+                Bytes(fixtures::metadata(modules).encode()),
+            )
+            .respond("chain_getBlockHash", genesis_hash())
+            .respond("state_getRuntimeVersion", runtime_version());
+        let build = |strict| {
+            async_std::task::block_on(
+                ClientBuilder::<Runtime>::new()
+                    .set_transport(transport.clone())
+                    .set_strict_event_types(strict)
+                    .build(),
+            )
+        };
+
+        assert!(build(false).is_ok());
+        match build(true) {
+            Err(Error::Events(EventsError::TypeSizesMissing(missing))) => {
+                assert_eq!(missing, vec!["Custom::Happened::CustomId".to_string()])
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+            Ok(_) => panic!("Client should not build with unknown event types"),
+        }
+    }
+
+    #[test]
+    fn test_mock_signed_extensions_mismatch() {
+        // a runtime without contracts has no `CheckBlockGasLimit`
+        let mut extensions = fixtures::node_signed_extensions();
+        extensions.pop();
+        let metadata = fixtures::metadata_with_signed_extensions(
+            fixtures::node_modules(),
+            extensions,
+        );
+        let transport = MockTransport::new();
+        transport
+            .respond("state_getMetadata", Bytes(metadata.encode()))
+            .respond("chain_getBlockHash", genesis_hash())
+            .respond("state_getRuntimeVersion", runtime_version());
+        let client = async_std::task::block_on(
+            ClientBuilder::<Runtime>::new()
+                .set_transport(transport)
+                .build(),
+        );
+
+        match client {
+            Err(Error::Metadata(MetadataError::SignedExtensionsMismatch {
+                runtime,
+                client,
+            })) => {
+                assert_eq!(runtime.last().unwrap(), "ChargeTransactionPayment");
+                assert_eq!(client.last().unwrap(), "CheckBlockGasLimit");
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+            Ok(_) => panic!("Client should not build with different signed extensions"),
+        }
+    }
 }
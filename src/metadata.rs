@@ -24,12 +24,15 @@ use std::{
 use codec::{
     Decode,
     Encode,
+    Input,
 };
 
 use frame_metadata::{
     DecodeDifferent,
+    DecodeDifferentArray,
     RuntimeMetadata,
     RuntimeMetadataPrefixed,
+    RuntimeMetadataV11,
     StorageEntryModifier,
     StorageEntryType,
     StorageHasher,
@@ -39,6 +42,9 @@ use sp_core::storage::StorageKey;
 
 use crate::Encoded;
 
+#[cfg(test)]
+pub(crate) mod fixtures;
+
 #[derive(Debug, thiserror::Error)]
 pub enum MetadataError {
     #[error("Error converting substrate metadata: {0}")]
@@ -51,12 +57,25 @@ pub enum MetadataError {
     CallNotFound(&'static str),
     #[error("Event not found")]
     EventNotFound(u8),
+    #[error("Module with errors not found")]
+    ModuleWithErrorsNotFound(u8),
+    #[error("Error not found")]
+    ErrorNotFound(u8),
     #[error("Storage not found")]
     StorageNotFound(&'static str),
     #[error("Storage type error")]
     StorageTypeError,
     #[error("Map value type error")]
     MapValueTypeError,
+    #[error("Constant not found")]
+    ConstantNotFound(&'static str),
+    #[error("Constant value type error: {0}")]
+    ConstantValueError(codec::Error),
+    #[error("Signed extensions mismatch: runtime {runtime:?}, client {client:?}")]
+    SignedExtensionsMismatch {
+        runtime: Vec<String>,
+        client: Vec<String>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -64,6 +83,8 @@ pub struct Metadata {
     modules: HashMap<String, ModuleMetadata>,
     modules_with_calls: HashMap<String, ModuleWithCalls>,
     modules_with_events: HashMap<String, ModuleWithEvents>,
+    modules_with_errors: HashMap<String, ModuleWithErrors>,
+    signed_extensions: Option<Vec<String>>,
 }
 
 impl Metadata {
@@ -101,6 +122,47 @@ impl Metadata {
             .ok_or(MetadataError::ModuleWithEventsNotFound(module_index))
     }
 
+    /// The errors of the module at `module_index`, as found in `DispatchError::Module`.
+    pub fn module_with_errors(
+        &self,
+        module_index: u8,
+    ) -> Result<&ModuleWithErrors, MetadataError> {
+        self.modules_with_errors
+            .values()
+            .find(|&module| module.index == module_index)
+            .ok_or(MetadataError::ModuleWithErrorsNotFound(module_index))
+    }
+
+    /// The identifiers of the signed extensions of the runtime, in order.
+    ///
+    /// Metadata prior to V11 does not list them.
+    pub fn signed_extensions(&self) -> Option<&[String]> {
+        self.signed_extensions.as_deref()
+    }
+
+    /// Fails if the signed extensions of the runtime differ from `identifiers`.
+    ///
+    /// Succeeds if the metadata does not list the signed extensions.
+    pub fn check_signed_extensions(
+        &self,
+        identifiers: &[&str],
+    ) -> Result<(), MetadataError> {
+        match &self.signed_extensions {
+            Some(extensions)
+                if !extensions
+                    .iter()
+                    .map(String::as_str)
+                    .eq(identifiers.iter().cloned()) =>
+            {
+                Err(MetadataError::SignedExtensionsMismatch {
+                    runtime: extensions.clone(),
+                    client: identifiers.iter().map(|id| id.to_string()).collect(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn pretty(&self) -> String {
         let mut string = String::new();
         for (name, module) in &self.modules {
@@ -134,7 +196,7 @@ impl Metadata {
 pub struct ModuleMetadata {
     name: String,
     storage: HashMap<String, StorageMetadata>,
-    // constants
+    constants: HashMap<String, ModuleConstantMetadata>,
 }
 
 impl ModuleMetadata {
@@ -143,6 +205,59 @@ impl ModuleMetadata {
             .get(key)
             .ok_or(MetadataError::StorageNotFound(key))
     }
+
+    pub fn constant(
+        &self,
+        name: &'static str,
+    ) -> Result<&ModuleConstantMetadata, MetadataError> {
+        self.constants
+            .get(name)
+            .ok_or(MetadataError::ConstantNotFound(name))
+    }
+
+    pub fn constants(&self) -> impl Iterator<Item = &ModuleConstantMetadata> {
+        self.constants.values()
+    }
+}
+
+/// A constant declared by a module, e.g. `Balances::ExistentialDeposit`.
+#[derive(Clone, Debug)]
+pub struct ModuleConstantMetadata {
+    name: String,
+    ty: String,
+    value: Vec<u8>,
+}
+
+impl ModuleConstantMetadata {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The type name of the constant as declared in the runtime, e.g. `T::Balance`.
+    pub fn ty(&self) -> &str {
+        &self.ty
+    }
+
+    /// The SCALE encoded value.
+    pub fn value_bytes(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Decodes the value as `V`.
+    ///
+    /// Fails if the value doesn't decode to exactly one `V`, e.g. a `u64` from a `u128`.
+    pub fn constant<V: Decode>(&self) -> Result<V, MetadataError> {
+        let mut value = &self.value[..];
+        let constant =
+            Decode::decode(&mut value).map_err(MetadataError::ConstantValueError)?;
+        if value.is_empty() {
+            Ok(constant)
+        } else {
+            Err(MetadataError::ConstantValueError(
+                "Constant value not fully consumed".into(),
+            ))
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -175,6 +290,11 @@ pub struct ModuleWithEvents {
 }
 
 impl ModuleWithEvents {
+    /// The index of the module in the `EventRecord`s of the runtime.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -190,6 +310,43 @@ impl ModuleWithEvents {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct ModuleWithErrors {
+    index: u8,
+    name: String,
+    errors: HashMap<u8, ModuleErrorMetadata>,
+}
+
+impl ModuleWithErrors {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn error(&self, index: u8) -> Result<&ModuleErrorMetadata, MetadataError> {
+        self.errors
+            .get(&index)
+            .ok_or(MetadataError::ErrorNotFound(index))
+    }
+}
+
+/// An error declared by a module, e.g. `Balances::InsufficientBalance`.
+#[derive(Clone, Debug)]
+pub struct ModuleErrorMetadata {
+    name: String,
+    documentation: Vec<String>,
+}
+
+impl ModuleErrorMetadata {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The documentation of the error, one entry per line.
+    pub fn documentation(&self) -> &[String] {
+        &self.documentation
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StorageMetadata {
     module_prefix: String,
@@ -200,6 +357,23 @@ pub struct StorageMetadata {
 }
 
 impl StorageMetadata {
+    pub fn get_value<V: Decode + Clone>(&self) -> Result<StorageValue<V>, MetadataError> {
+        match &self.ty {
+            StorageEntryType::Plain(_) => {
+                let module_prefix = self.module_prefix.as_bytes().to_vec();
+                let storage_prefix = self.storage_prefix.as_bytes().to_vec();
+                let default = Decode::decode(&mut &self.default[..])
+                    .map_err(|_| MetadataError::MapValueTypeError)?;
+                Ok(StorageValue {
+                    module_prefix,
+                    storage_prefix,
+                    default,
+                })
+            }
+            _ => Err(MetadataError::StorageTypeError),
+        }
+    }
+
     pub fn get_map<K: Encode, V: Decode + Clone>(
         &self,
     ) -> Result<StorageMap<K, V>, MetadataError> {
@@ -221,6 +395,51 @@ impl StorageMetadata {
             _ => Err(MetadataError::StorageTypeError),
         }
     }
+
+    pub fn get_double_map<K1: Encode, K2: Encode, V: Decode + Clone>(
+        &self,
+    ) -> Result<StorageDoubleMap<K1, K2, V>, MetadataError> {
+        match &self.ty {
+            StorageEntryType::DoubleMap {
+                hasher,
+                key2_hasher,
+                ..
+            } => {
+                let module_prefix = self.module_prefix.as_bytes().to_vec();
+                let storage_prefix = self.storage_prefix.as_bytes().to_vec();
+                let hasher = hasher.to_owned();
+                let key2_hasher = key2_hasher.to_owned();
+                let default = Decode::decode(&mut &self.default[..])
+                    .map_err(|_| MetadataError::MapValueTypeError)?;
+                Ok(StorageDoubleMap {
+                    _marker: PhantomData,
+                    module_prefix,
+                    storage_prefix,
+                    hasher,
+                    key2_hasher,
+                    default,
+                })
+            }
+            _ => Err(MetadataError::StorageTypeError),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StorageValue<V> {
+    module_prefix: Vec<u8>,
+    storage_prefix: Vec<u8>,
+    default: V,
+}
+
+impl<V: Decode + Clone> StorageValue<V> {
+    pub fn key(&self) -> StorageKey {
+        StorageKey(entry_prefix(&self.module_prefix, &self.storage_prefix))
+    }
+
+    pub fn default(&self) -> V {
+        self.default.clone()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -234,25 +453,70 @@ pub struct StorageMap<K, V> {
 
 impl<K: Encode, V: Decode + Clone> StorageMap<K, V> {
     pub fn key(&self, key: K) -> StorageKey {
-        let mut bytes = sp_core::twox_128(&self.module_prefix).to_vec();
-        bytes.extend(&sp_core::twox_128(&self.storage_prefix)[..]);
-        let encoded_key = key.encode();
-        let hash = match self.hasher {
-            StorageHasher::Blake2_128 => sp_core::blake2_128(&encoded_key).to_vec(),
-            StorageHasher::Blake2_128Concat => {
-                // copied from substrate Blake2_128Concat::hash since StorageHasher is not public
-                sp_core::blake2_128(&encoded_key)
-                    .iter()
-                    .chain(&encoded_key)
-                    .cloned()
-                    .collect::<Vec<_>>()
-            }
-            StorageHasher::Blake2_256 => sp_core::blake2_256(&encoded_key).to_vec(),
-            StorageHasher::Twox128 => sp_core::twox_128(&encoded_key).to_vec(),
-            StorageHasher::Twox256 => sp_core::twox_256(&encoded_key).to_vec(),
-            StorageHasher::Twox64Concat => sp_core::twox_64(&encoded_key).to_vec(),
+        let StorageKey(mut bytes) = self.prefix();
+        bytes.extend(hash_key(&self.hasher, &key.encode()));
+        StorageKey(bytes)
+    }
+
+    /// The key shared by all entries of the map.
+    pub fn prefix(&self) -> StorageKey {
+        StorageKey(entry_prefix(&self.module_prefix, &self.storage_prefix))
+    }
+
+    pub fn default(&self) -> V {
+        self.default.clone()
+    }
+}
+
+impl<K: Encode + Decode, V: Decode + Clone> StorageMap<K, V> {
+    /// Recovers the map key from the storage key of an entry.
+    ///
+    /// Only possible for the concat hashers, returns `None` for other hashers and for keys
+    /// which don't decode to exactly one `K`.
+    pub fn decode_key(&self, key: &StorageKey) -> Option<K> {
+        let hash_len = match self.hasher {
+            StorageHasher::Blake2_128Concat => 16,
+            StorageHasher::Twox64Concat => 8,
+            _ => return None,
         };
-        bytes.extend(hash);
+        let prefix = self.prefix();
+        if !key.0.starts_with(&prefix.0) {
+            return None
+        }
+        let mut encoded_key = key.0.get(prefix.0.len() + hash_len..)?;
+        let map_key = Decode::decode(&mut encoded_key).ok()?;
+        // left over bytes mean the key is not a `K`, e.g. of another map or hasher
+        if encoded_key.is_empty() {
+            Some(map_key)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StorageDoubleMap<K1, K2, V> {
+    _marker: PhantomData<(K1, K2)>,
+    module_prefix: Vec<u8>,
+    storage_prefix: Vec<u8>,
+    hasher: StorageHasher,
+    key2_hasher: StorageHasher,
+    default: V,
+}
+
+impl<K1: Encode, K2: Encode, V: Decode + Clone> StorageDoubleMap<K1, K2, V> {
+    pub fn key(&self, key1: K1, key2: K2) -> StorageKey {
+        let StorageKey(mut bytes) = self.prefix(key1);
+        bytes.extend(hash_key(&self.key2_hasher, &key2.encode()));
+        StorageKey(bytes)
+    }
+
+    /// The key shared by all entries with the first key `key1`.
+    ///
+    /// Can be used to query the keys of all entries under `key1`.
+    pub fn prefix(&self, key1: K1) -> StorageKey {
+        let mut bytes = entry_prefix(&self.module_prefix, &self.storage_prefix);
+        bytes.extend(hash_key(&self.hasher, &key1.encode()));
         StorageKey(bytes)
     }
 
@@ -261,6 +525,37 @@ impl<K: Encode, V: Decode + Clone> StorageMap<K, V> {
     }
 }
 
+/// The prefix of all keys of a storage entry, `twox_128(module) ++ twox_128(storage)`.
+fn entry_prefix(module_prefix: &[u8], storage_prefix: &[u8]) -> Vec<u8> {
+    let mut bytes = sp_core::twox_128(module_prefix).to_vec();
+    bytes.extend(&sp_core::twox_128(storage_prefix)[..]);
+    bytes
+}
+
+/// Hashes an encoded storage key the same way as the runtime's `StorageHasher`.
+///
+/// The concat variants append the encoded key to the hash, so the key can be recovered
+/// from the storage key.
+pub(crate) fn hash_key(hasher: &StorageHasher, encoded_key: &[u8]) -> Vec<u8> {
+    fn concat(hash: &[u8], encoded_key: &[u8]) -> Vec<u8> {
+        hash.iter().chain(encoded_key).cloned().collect()
+    }
+    // `Identity` is not yet part of the pinned `frame_metadata`, when added it must return
+    // the encoded key unchanged.
+    match hasher {
+        StorageHasher::Blake2_128 => sp_core::blake2_128(encoded_key).to_vec(),
+        StorageHasher::Blake2_128Concat => {
+            concat(&sp_core::blake2_128(encoded_key), encoded_key)
+        }
+        StorageHasher::Blake2_256 => sp_core::blake2_256(encoded_key).to_vec(),
+        StorageHasher::Twox128 => sp_core::twox_128(encoded_key).to_vec(),
+        StorageHasher::Twox256 => sp_core::twox_256(encoded_key).to_vec(),
+        StorageHasher::Twox64Concat => {
+            concat(&sp_core::twox_64(encoded_key), encoded_key)
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ModuleEventMetadata {
     pub name: String,
@@ -283,27 +578,28 @@ pub enum EventArg {
     Primitive(String),
     Vec(Box<EventArg>),
     Tuple(Vec<EventArg>),
+    Option(Box<EventArg>),
+    Compact(Box<EventArg>),
+    /// Fixed size array, `[T; N]`.
+    Array(usize, Box<EventArg>),
 }
 
 impl FromStr for EventArg {
     type Err = ConversionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.starts_with("Vec<") {
-            if s.ends_with('>') {
-                Ok(EventArg::Vec(Box::new(s[4..s.len() - 1].parse()?)))
-            } else {
-                Err(ConversionError::InvalidEventArg(
-                    s.to_string(),
-                    "Expected closing `>` for `Vec`",
-                ))
-            }
+        let s = s.trim();
+        if let Some(arg) = generic_arg(s, "Vec")? {
+            Ok(EventArg::Vec(Box::new(arg.parse()?)))
+        } else if let Some(arg) = generic_arg(s, "Option")? {
+            Ok(EventArg::Option(Box::new(arg.parse()?)))
+        } else if let Some(arg) = generic_arg(s, "Compact")? {
+            Ok(EventArg::Compact(Box::new(arg.parse()?)))
         } else if s.starts_with('(') {
             if s.ends_with(')') {
                 let mut args = Vec::new();
-                for arg in s[1..s.len() - 1].split(',') {
-                    let arg = arg.trim().parse()?;
-                    args.push(arg)
+                for arg in split_top_level(s, &s[1..s.len() - 1], ',')? {
+                    args.push(arg.parse()?)
                 }
                 Ok(EventArg::Tuple(args))
             } else {
@@ -312,18 +608,100 @@ impl FromStr for EventArg {
                     "Expecting closing `)` for tuple",
                 ))
             }
+        } else if s.starts_with('[') {
+            if s.ends_with(']') {
+                match split_top_level(s, &s[1..s.len() - 1], ';')?.as_slice() {
+                    [arg, len] => {
+                        let len = len.parse().map_err(|_| {
+                            ConversionError::InvalidEventArg(
+                                s.to_string(),
+                                "Expected a length for array",
+                            )
+                        })?;
+                        Ok(EventArg::Array(len, Box::new(arg.parse()?)))
+                    }
+                    _ => {
+                        Err(ConversionError::InvalidEventArg(
+                            s.to_string(),
+                            "Expected `[T; N]` for array",
+                        ))
+                    }
+                }
+            } else {
+                Err(ConversionError::InvalidEventArg(
+                    s.to_string(),
+                    "Expecting closing `]` for array",
+                ))
+            }
         } else {
             Ok(EventArg::Primitive(s.to_string()))
         }
     }
 }
 
+/// Returns the type argument of `s` if it is the generic type `name<T>`.
+fn generic_arg<'a>(s: &'a str, name: &str) -> Result<Option<&'a str>, ConversionError> {
+    if !s.starts_with(name) || !s[name.len()..].starts_with('<') {
+        return Ok(None)
+    }
+    if s.ends_with('>') {
+        Ok(Some(&s[name.len() + 1..s.len() - 1]))
+    } else {
+        Err(ConversionError::InvalidEventArg(
+            s.to_string(),
+            "Expected closing `>` for generic type",
+        ))
+    }
+}
+
+/// Splits `inner` on `separator`, ignoring separators nested in brackets of type `ty`.
+fn split_top_level<'a>(
+    ty: &str,
+    inner: &'a str,
+    separator: char,
+) -> Result<Vec<&'a str>, ConversionError> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => {
+                depth = depth.checked_sub(1).ok_or_else(|| {
+                    ConversionError::InvalidEventArg(
+                        ty.to_string(),
+                        "Unbalanced brackets",
+                    )
+                })?
+            }
+            c if c == separator && depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 {
+        return Err(ConversionError::InvalidEventArg(
+            ty.to_string(),
+            "Unbalanced brackets",
+        ))
+    }
+    parts.push(inner[start..].trim());
+    // drops the empty part after a trailing separator, as in `(T,)`
+    Ok(parts.into_iter().filter(|part| !part.is_empty()).collect())
+}
+
 impl EventArg {
     /// Returns all primitive types for this EventArg
     pub fn primitives(&self) -> Vec<String> {
         match self {
             EventArg::Primitive(p) => vec![p.clone()],
-            EventArg::Vec(arg) => arg.primitives(),
+            EventArg::Vec(arg) | EventArg::Option(arg) | EventArg::Array(_, arg) => {
+                arg.primitives()
+            }
+            // compact encoding doesn't depend on the size of the type
+            EventArg::Compact(_) => Vec::new(),
             EventArg::Tuple(args) => {
                 let mut primitives = Vec::new();
                 for arg in args {
@@ -337,16 +715,56 @@ impl EventArg {
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConversionError {
+    #[error("Scale codec error: {0}")]
+    Codec(#[from] codec::Error),
     #[error("Invalid prefix")]
     InvalidPrefix,
-    #[error("Invalid version")]
-    InvalidVersion,
+    #[error("Unsupported metadata version {0}")]
+    InvalidVersion(u8),
     #[error("Expected DecodeDifferent::Decoded")]
     ExpectedDecoded,
     #[error("Invalid event arg {0}")]
     InvalidEventArg(String, &'static str),
 }
 
+/// Metadata V10, which is decoded with the module layout of the pinned `frame_metadata`.
+/// V11 appends `ExtrinsicMetadata` after the same modules, so V10 is its prefix.
+///
+/// `frame_metadata` only keeps a placeholder for versions prior to V11 which cannot be
+/// decoded, so the layout is defined here.
+#[derive(Decode)]
+struct RuntimeMetadataV10 {
+    modules: DecodeDifferentArray<frame_metadata::ModuleMetadata>,
+}
+
+impl Metadata {
+    /// Decodes metadata as returned by `state_getMetadata`.
+    ///
+    /// Supports V10 and V11.
+    pub fn decode(bytes: &[u8]) -> Result<Self, MetadataError> {
+        let input = &mut &bytes[..];
+        let prefix = u32::decode(input).map_err(ConversionError::from)?;
+        if prefix != META_RESERVED {
+            return Err(ConversionError::InvalidPrefix.into())
+        }
+        // the version is the index of the `RuntimeMetadata` variant
+        let version = input.read_byte().map_err(ConversionError::from)?;
+        match version {
+            10 => {
+                let meta =
+                    RuntimeMetadataV10::decode(input).map_err(ConversionError::from)?;
+                convert_modules(convert(meta.modules)?)
+            }
+            11 => {
+                let meta =
+                    RuntimeMetadataV11::decode(input).map_err(ConversionError::from)?;
+                convert_v11(meta)
+            }
+            _ => Err(ConversionError::InvalidVersion(version).into()),
+        }
+    }
+}
+
 impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
     type Error = MetadataError;
 
@@ -354,73 +772,108 @@ impl TryFrom<RuntimeMetadataPrefixed> for Metadata {
         if metadata.0 != META_RESERVED {
             return Err(ConversionError::InvalidPrefix.into())
         }
-        let meta = match metadata.1 {
-            RuntimeMetadata::V11(meta) => meta,
-            _ => return Err(ConversionError::InvalidVersion.into()),
-        };
-        let mut modules = HashMap::new();
-        let mut modules_with_calls = HashMap::new();
-        let mut modules_with_events = HashMap::new();
-        for module in convert(meta.modules)?.into_iter() {
-            let module_name = convert(module.name.clone())?;
-
-            let mut storage_map = HashMap::new();
-            if let Some(storage) = module.storage {
-                let storage = convert(storage)?;
-                let module_prefix = convert(storage.prefix)?;
-                for entry in convert(storage.entries)?.into_iter() {
-                    let storage_prefix = convert(entry.name.clone())?;
-                    let entry = convert_entry(
-                        module_prefix.clone(),
-                        storage_prefix.clone(),
-                        entry,
-                    )?;
-                    storage_map.insert(storage_prefix, entry);
-                }
+        match metadata.1 {
+            RuntimeMetadata::V11(meta) => convert_v11(meta),
+            // earlier versions cannot be decoded by `frame_metadata`, see `Metadata::decode`
+            other => Err(ConversionError::InvalidVersion(other.encode()[0]).into()),
+        }
+    }
+}
+
+fn convert_v11(meta: RuntimeMetadataV11) -> Result<Metadata, MetadataError> {
+    let mut metadata = convert_modules(convert(meta.modules)?)?;
+    let mut signed_extensions = Vec::new();
+    for extension in meta.extrinsic.signed_extensions {
+        signed_extensions.push(convert(extension)?);
+    }
+    metadata.signed_extensions = Some(signed_extensions);
+    Ok(metadata)
+}
+
+fn convert_modules(
+    module_metadata: Vec<frame_metadata::ModuleMetadata>,
+) -> Result<Metadata, MetadataError> {
+    let mut modules = HashMap::new();
+    let mut modules_with_calls = HashMap::new();
+    let mut modules_with_events = HashMap::new();
+    let mut modules_with_errors = HashMap::new();
+    for (module_index, module) in module_metadata.into_iter().enumerate() {
+        let module_name = convert(module.name.clone())?;
+
+        let mut storage_map = HashMap::new();
+        if let Some(storage) = module.storage {
+            let storage = convert(storage)?;
+            let module_prefix = convert(storage.prefix)?;
+            for entry in convert(storage.entries)?.into_iter() {
+                let storage_prefix = convert(entry.name.clone())?;
+                let entry =
+                    convert_entry(module_prefix.clone(), storage_prefix.clone(), entry)?;
+                storage_map.insert(storage_prefix, entry);
+            }
+        }
+        let mut constant_map = HashMap::new();
+        for constant in convert(module.constants)?.into_iter() {
+            let constant = convert_constant(constant)?;
+            constant_map.insert(constant.name.clone(), constant);
+        }
+        modules.insert(
+            module_name.clone(),
+            ModuleMetadata {
+                name: module_name.clone(),
+                storage: storage_map,
+                constants: constant_map,
+            },
+        );
+
+        if let Some(calls) = module.calls {
+            let mut call_map = HashMap::new();
+            for (index, call) in convert(calls)?.into_iter().enumerate() {
+                let name = convert(call.name)?;
+                call_map.insert(name, index as u8);
             }
-            modules.insert(
+            modules_with_calls.insert(
                 module_name.clone(),
-                ModuleMetadata {
-                    name: module_name.clone(),
-                    storage: storage_map,
+                ModuleWithCalls {
+                    index: modules_with_calls.len() as u8,
+                    calls: call_map,
                 },
             );
-
-            if let Some(calls) = module.calls {
-                let mut call_map = HashMap::new();
-                for (index, call) in convert(calls)?.into_iter().enumerate() {
-                    let name = convert(call.name)?;
-                    call_map.insert(name, index as u8);
-                }
-                modules_with_calls.insert(
-                    module_name.clone(),
-                    ModuleWithCalls {
-                        index: modules_with_calls.len() as u8,
-                        calls: call_map,
-                    },
-                );
-            }
-            if let Some(events) = module.event {
-                let mut event_map = HashMap::new();
-                for (index, event) in convert(events)?.into_iter().enumerate() {
-                    event_map.insert(index as u8, convert_event(event)?);
-                }
-                modules_with_events.insert(
-                    module_name.clone(),
-                    ModuleWithEvents {
-                        index: modules_with_events.len() as u8,
-                        name: module_name.clone(),
-                        events: event_map,
-                    },
-                );
+        }
+        if let Some(events) = module.event {
+            let mut event_map = HashMap::new();
+            for (index, event) in convert(events)?.into_iter().enumerate() {
+                event_map.insert(index as u8, convert_event(event)?);
             }
+            modules_with_events.insert(
+                module_name.clone(),
+                ModuleWithEvents {
+                    index: modules_with_events.len() as u8,
+                    name: module_name.clone(),
+                    events: event_map,
+                },
+            );
+        }
+        // dispatch errors refer to the module by its index among all modules
+        let mut error_map = HashMap::new();
+        for (index, error) in convert(module.errors)?.into_iter().enumerate() {
+            error_map.insert(index as u8, convert_error(error)?);
         }
-        Ok(Metadata {
-            modules,
-            modules_with_calls,
-            modules_with_events,
-        })
+        modules_with_errors.insert(
+            module_name.clone(),
+            ModuleWithErrors {
+                index: module_index as u8,
+                name: module_name.clone(),
+                errors: error_map,
+            },
+        );
     }
+    Ok(Metadata {
+        modules,
+        modules_with_calls,
+        modules_with_events,
+        modules_with_errors,
+        signed_extensions: None,
+    })
 }
 
 fn convert<B: 'static, O: 'static>(
@@ -444,6 +897,25 @@ fn convert_event(
     Ok(ModuleEventMetadata { name, arguments })
 }
 
+fn convert_error(
+    error: frame_metadata::ErrorMetadata,
+) -> Result<ModuleErrorMetadata, ConversionError> {
+    Ok(ModuleErrorMetadata {
+        name: convert(error.name)?,
+        documentation: convert(error.documentation)?,
+    })
+}
+
+fn convert_constant(
+    constant: frame_metadata::ModuleConstantMetadata,
+) -> Result<ModuleConstantMetadata, ConversionError> {
+    Ok(ModuleConstantMetadata {
+        name: convert(constant.name)?,
+        ty: convert(constant.ty)?,
+        value: convert(constant.value)?,
+    })
+}
+
 fn convert_entry(
     module_prefix: String,
     storage_prefix: String,
@@ -458,3 +930,405 @@ fn convert_entry(
         default,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_node_modules(metadata: &Metadata) {
+        let balances = metadata.module_with_calls("Balances").unwrap();
+        assert_eq!(balances.index, 1);
+        assert!(balances.call("transfer", ()).is_ok());
+        assert!(metadata
+            .module("System")
+            .unwrap()
+            .storage("AccountNonce")
+            .is_ok());
+        let events = metadata.module_with_events(2).unwrap();
+        assert_eq!(events.name(), "Contracts");
+        assert_eq!(events.event(1).unwrap().name, "CodeStored");
+    }
+
+    #[test]
+    fn test_decode_v10() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_v10_bytes()).unwrap();
+        assert_node_modules(&metadata);
+        let existential_deposit = metadata
+            .module("Balances")
+            .unwrap()
+            .constant("ExistentialDeposit")
+            .unwrap()
+            .constant::<u128>()
+            .unwrap();
+        assert_eq!(existential_deposit, 500);
+        let contract_info = metadata
+            .module("Contracts")
+            .unwrap()
+            .storage("ContractInfoOf")
+            .unwrap();
+        match contract_info.ty {
+            StorageEntryType::Map { ref hasher, .. } => {
+                assert_eq!(hasher, &StorageHasher::Twox64Concat)
+            }
+            _ => panic!("Expected a map"),
+        }
+    }
+
+    #[test]
+    fn test_decode_v11() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        assert_node_modules(&metadata);
+    }
+
+    #[test]
+    fn test_try_from_v11() {
+        let metadata = Metadata::try_from(fixtures::node_metadata()).unwrap();
+        assert_node_modules(&metadata);
+    }
+
+    #[test]
+    fn test_signed_extensions() {
+        let node = fixtures::node_signed_extensions();
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        assert_eq!(
+            metadata.signed_extensions().unwrap().to_vec(),
+            node.iter().map(|id| id.to_string()).collect::<Vec<_>>()
+        );
+        assert!(metadata.check_signed_extensions(&node).is_ok());
+        match metadata.check_signed_extensions(&node[..6]) {
+            Err(MetadataError::SignedExtensionsMismatch { runtime, client }) => {
+                assert_eq!(runtime.len(), 7);
+                assert_eq!(client.len(), 6);
+            }
+            other => panic!("Expected a mismatch, got {:?}", other),
+        }
+
+        // V10 does not list the signed extensions, so any are accepted
+        let metadata = Metadata::decode(&fixtures::node_metadata_v10_bytes()).unwrap();
+        assert!(metadata.signed_extensions().is_none());
+        assert!(metadata.check_signed_extensions(&node[..6]).is_ok());
+    }
+
+    #[test]
+    fn test_module_errors() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let balances = metadata.module_with_errors(1).unwrap();
+        assert_eq!(balances.name(), "Balances");
+        let error = balances.error(0).unwrap();
+        assert_eq!(error.name(), "InsufficientBalance");
+        assert_eq!(
+            error.documentation(),
+            &[" Balance too low to send value".to_string()]
+        );
+        assert!(balances.error(2).is_err());
+        // modules without errors keep their index
+        let contracts = metadata.module_with_errors(2).unwrap();
+        assert_eq!(contracts.name(), "Contracts");
+        assert!(metadata.module_with_errors(3).is_err());
+    }
+
+    #[test]
+    fn test_module_constants() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let balances = metadata.module("Balances").unwrap();
+        let deposit = balances.constant("ExistentialDeposit").unwrap();
+        assert_eq!(deposit.ty(), "T::Balance");
+        assert_eq!(deposit.value_bytes(), &500u128.encode()[..]);
+        assert_eq!(deposit.constant::<u128>().unwrap(), 500);
+        assert_eq!(balances.constants().count(), 2);
+        assert!(match deposit.constant::<(u128, u8)>() {
+            Err(MetadataError::ConstantValueError(_)) => true,
+            _ => false,
+        });
+        // a narrower type would otherwise decode the truncated value
+        assert!(match deposit.constant::<u64>() {
+            Err(MetadataError::ConstantValueError(_)) => true,
+            _ => false,
+        });
+        assert!(match balances.constant("TransactionBaseFee") {
+            Err(MetadataError::ConstantNotFound("TransactionBaseFee")) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_hash_key_matches_runtime_hashers() {
+        use frame_support::{
+            Blake2_128,
+            Blake2_128Concat,
+            Blake2_256,
+            StorageHasher as _,
+            Twox128,
+            Twox256,
+            Twox64Concat,
+        };
+
+        let key = (1u32, [2u8; 32]).encode();
+        let hashers: Vec<(StorageHasher, Vec<u8>)> = vec![
+            (StorageHasher::Blake2_128, Blake2_128::hash(&key).to_vec()),
+            (
+                StorageHasher::Blake2_128Concat,
+                Blake2_128Concat::hash(&key).to_vec(),
+            ),
+            (StorageHasher::Blake2_256, Blake2_256::hash(&key).to_vec()),
+            (StorageHasher::Twox128, Twox128::hash(&key).to_vec()),
+            (StorageHasher::Twox256, Twox256::hash(&key).to_vec()),
+            (
+                StorageHasher::Twox64Concat,
+                Twox64Concat::hash(&key).to_vec(),
+            ),
+        ];
+        for (hasher, expected) in hashers {
+            assert_eq!(hash_key(&hasher, &key), expected, "{:?}", hasher);
+        }
+    }
+
+    #[test]
+    fn test_concat_hashers_append_key() {
+        let key = 42u64.encode();
+        let twox = hash_key(&StorageHasher::Twox64Concat, &key);
+        assert_eq!(&twox[..8], &sp_core::twox_64(&key)[..]);
+        assert_eq!(&twox[8..], &key[..]);
+        let blake = hash_key(&StorageHasher::Blake2_128Concat, &key);
+        assert_eq!(&blake[..16], &sp_core::blake2_128(&key)[..]);
+        assert_eq!(&blake[16..], &key[..]);
+    }
+
+    #[test]
+    fn test_storage_map_key() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let map = metadata
+            .module("Balances")
+            .unwrap()
+            .storage("FreeBalance")
+            .unwrap()
+            .get_map::<[u8; 32], u128>()
+            .unwrap();
+        let key = map.key([1u8; 32]);
+        // twox_128("Balances") ++ twox_128("FreeBalance") ++ blake2_256(key)
+        assert_eq!(
+            hex::encode(&key.0[..16]),
+            "c2261276cc9d1f8598ea4b6a74b15c2f"
+        );
+        assert_eq!(&key.0[16..32], &sp_core::twox_128(b"FreeBalance")[..]);
+        assert_eq!(&key.0[32..], &sp_core::blake2_256(&[1u8; 32])[..]);
+    }
+
+    #[test]
+    fn test_storage_map_decode_key() {
+        let metadata = convert_modules(vec![fixtures::storage_module(
+            "Identity",
+            vec![
+                fixtures::map(
+                    "Dids",
+                    StorageHasher::Twox64Concat,
+                    "Did",
+                    "DidDocument",
+                    0u8,
+                ),
+                fixtures::map(
+                    "Catalogs",
+                    StorageHasher::Blake2_128Concat,
+                    "CatalogId",
+                    "Catalog",
+                    0u8,
+                ),
+                fixtures::map("Nonces", StorageHasher::Blake2_256, "Did", "u32", 0u32),
+            ],
+        )])
+        .unwrap();
+        let module = metadata.module("Identity").unwrap();
+
+        let dids = module
+            .storage("Dids")
+            .unwrap()
+            .get_map::<[u8; 32], u8>()
+            .unwrap();
+        let key = dids.key([7u8; 32]);
+        assert!(key.0.starts_with(&dids.prefix().0));
+        assert_eq!(dids.decode_key(&key), Some([7u8; 32]));
+
+        let catalogs = module
+            .storage("Catalogs")
+            .unwrap()
+            .get_map::<u32, u8>()
+            .unwrap();
+        assert_eq!(catalogs.decode_key(&catalogs.key(42)), Some(42));
+        assert_eq!(catalogs.decode_key(&dids.key([7u8; 32])), None);
+        // a wider key under the same prefix only partially decodes
+        let wide_catalogs = module
+            .storage("Catalogs")
+            .unwrap()
+            .get_map::<u64, u8>()
+            .unwrap();
+        assert_eq!(catalogs.decode_key(&wide_catalogs.key(42)), None);
+
+        let nonces = module
+            .storage("Nonces")
+            .unwrap()
+            .get_map::<[u8; 32], u32>()
+            .unwrap();
+        assert_eq!(nonces.decode_key(&nonces.key([7u8; 32])), None);
+    }
+
+    #[test]
+    fn test_storage_value_key() {
+        let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+        let storage = metadata
+            .module("System")
+            .unwrap()
+            .storage("Number")
+            .unwrap();
+        assert!(match storage.get_map::<u32, u32>() {
+            Err(MetadataError::StorageTypeError) => true,
+            _ => false,
+        });
+        let value = storage.get_value::<u32>().unwrap();
+        assert_eq!(value.default(), 0);
+        // twox_128("System") ++ twox_128("Number")
+        assert_eq!(
+            hex::encode(&value.key().0),
+            "26aa394eea5630e07c48ae0c9558cef702a5c1b19ab7a04f536c519aca4983ac"
+        );
+    }
+
+    #[test]
+    fn test_storage_double_map_key() {
+        let metadata = convert_modules(vec![fixtures::storage_module(
+            "Identity",
+            vec![fixtures::double_map(
+                "ClaimConsumers",
+                StorageHasher::Blake2_128Concat,
+                "Did",
+                StorageHasher::Twox64Concat,
+                "T::AccountId",
+                "Moment",
+                0u64,
+            )],
+        )])
+        .unwrap();
+        let storage = metadata
+            .module("Identity")
+            .unwrap()
+            .storage("ClaimConsumers")
+            .unwrap();
+        assert!(match storage.get_map::<[u8; 32], u64>() {
+            Err(MetadataError::StorageTypeError) => true,
+            _ => false,
+        });
+        let map = storage.get_double_map::<[u8; 32], [u8; 32], u64>().unwrap();
+        assert_eq!(map.default(), 0);
+
+        let key1 = [1u8; 32];
+        let key2 = [2u8; 32];
+        let prefix = map.prefix(key1);
+        let key = map.key(key1, key2);
+        assert_eq!(&prefix.0[..16], &sp_core::twox_128(b"Identity")[..]);
+        assert_eq!(&prefix.0[16..32], &sp_core::twox_128(b"ClaimConsumers")[..]);
+        assert_eq!(
+            &prefix.0[32..],
+            &hash_key(&StorageHasher::Blake2_128Concat, &key1)[..]
+        );
+        assert!(key.0.starts_with(&prefix.0));
+        assert_eq!(
+            &key.0[prefix.0.len()..],
+            &hash_key(&StorageHasher::Twox64Concat, &key2)[..]
+        );
+    }
+
+    fn arg(s: &str) -> EventArg {
+        s.parse().unwrap()
+    }
+
+    fn primitive(s: &str) -> EventArg {
+        EventArg::Primitive(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_event_args() {
+        assert_eq!(arg("AccountId"), primitive("AccountId"));
+        assert_eq!(arg("Vec<u8>"), EventArg::Vec(Box::new(primitive("u8"))));
+        assert_eq!(
+            arg("(Vec<(AccountId, Balance)>, u32)"),
+            EventArg::Tuple(vec![
+                EventArg::Vec(Box::new(EventArg::Tuple(vec![
+                    primitive("AccountId"),
+                    primitive("Balance"),
+                ]))),
+                primitive("u32"),
+            ])
+        );
+        assert_eq!(
+            arg("Option<Vec<Did>>"),
+            EventArg::Option(Box::new(EventArg::Vec(Box::new(primitive("Did")))))
+        );
+        assert_eq!(
+            arg("Compact<Balance>"),
+            EventArg::Compact(Box::new(primitive("Balance")))
+        );
+        assert_eq!(
+            arg("[u8; 32]"),
+            EventArg::Array(32, Box::new(primitive("u8")))
+        );
+        assert_eq!(
+            arg("[(u8, u16); 2]"),
+            EventArg::Array(
+                2,
+                Box::new(EventArg::Tuple(vec![primitive("u8"), primitive("u16")]))
+            )
+        );
+        assert_eq!(arg("(u8,)"), EventArg::Tuple(vec![primitive("u8")]));
+        assert_eq!(arg("()"), EventArg::Tuple(vec![]));
+        // other generic types are opaque
+        assert_eq!(arg("BalanceOf<T>"), primitive("BalanceOf<T>"));
+        assert_eq!(arg("Vector"), primitive("Vector"));
+    }
+
+    #[test]
+    fn test_parse_invalid_event_args() {
+        for s in &[
+            "Vec<u8",
+            "(u8, u32",
+            "[u8; 32",
+            "[u8]",
+            "[u8; n]",
+            "(Vec<u8)>)",
+        ] {
+            assert!(
+                match s.parse::<EventArg>() {
+                    Err(ConversionError::InvalidEventArg(..)) => true,
+                    _ => false,
+                },
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_event_arg_primitives() {
+        assert_eq!(
+            arg("(Vec<(AccountId, Compact<Balance>)>, Option<[Hash; 2]>)").primitives(),
+            vec!["AccountId".to_string(), "Hash".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_decode_unsupported_version() {
+        let bytes = (META_RESERVED, 9u8).encode();
+        assert!(match Metadata::decode(&bytes) {
+            Err(MetadataError::Conversion(ConversionError::InvalidVersion(9))) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_decode_invalid_prefix() {
+        let mut bytes = fixtures::node_metadata_bytes();
+        bytes[0] = 0;
+        assert!(match Metadata::decode(&bytes) {
+            Err(MetadataError::Conversion(ConversionError::InvalidPrefix)) => true,
+            _ => false,
+        });
+    }
+}
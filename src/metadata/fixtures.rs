@@ -0,0 +1,611 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Metadata fixtures for tests which run without a node.
+//!
+//! `node_runtime_metadata_bytes` is the metadata of the substrate `node_runtime` itself. The
+//! hand-built fixtures mirror the modules of the node that the client depends on and are
+//! meant for targeted cases, such as custom event types or signed extensions.
+
+use codec::Encode;
+use frame_metadata::{
+    DecodeDifferent,
+    ErrorMetadata,
+    EventMetadata,
+    ExtrinsicMetadata,
+    FunctionArgumentMetadata,
+    FunctionMetadata,
+    ModuleConstantMetadata,
+    ModuleMetadata,
+    RuntimeMetadata,
+    RuntimeMetadataPrefixed,
+    RuntimeMetadataV11,
+    StorageEntryMetadata,
+    StorageEntryModifier,
+    StorageEntryType,
+    StorageHasher,
+    StorageMetadata,
+    META_RESERVED,
+};
+
+fn decoded<B: 'static, O: 'static>(value: O) -> DecodeDifferent<B, O> {
+    DecodeDifferent::Decoded(value)
+}
+
+fn string<B: 'static>(value: &str) -> DecodeDifferent<B, String> {
+    decoded(value.to_string())
+}
+
+fn strings<B: 'static>(values: &[&str]) -> DecodeDifferent<B, Vec<String>> {
+    decoded(values.iter().map(|s| s.to_string()).collect())
+}
+
+fn call(name: &str, arguments: &[(&str, &str)]) -> FunctionMetadata {
+    FunctionMetadata {
+        name: string(name),
+        arguments: decoded(
+            arguments
+                .iter()
+                .map(|(name, ty)| {
+                    FunctionArgumentMetadata {
+                        name: string(name),
+                        ty: string(ty),
+                    }
+                })
+                .collect(),
+        ),
+        documentation: strings(&[]),
+    }
+}
+
+pub fn event(name: &str, arguments: &[&str]) -> EventMetadata {
+    EventMetadata {
+        name: string(name),
+        arguments: strings(arguments),
+        documentation: strings(&[]),
+    }
+}
+
+fn constant<V: Encode>(name: &str, ty: &str, value: V) -> ModuleConstantMetadata {
+    ModuleConstantMetadata {
+        name: string(name),
+        ty: string(ty),
+        value: decoded(value.encode()),
+        documentation: strings(&[]),
+    }
+}
+
+fn error(name: &str, documentation: &[&str]) -> ErrorMetadata {
+    ErrorMetadata {
+        name: string(name),
+        documentation: strings(documentation),
+    }
+}
+
+fn plain<V: Encode>(name: &str, value: &str, default: V) -> StorageEntryMetadata {
+    StorageEntryMetadata {
+        name: string(name),
+        modifier: StorageEntryModifier::Default,
+        ty: StorageEntryType::Plain(string(value)),
+        default: decoded(default.encode()),
+        documentation: strings(&[]),
+    }
+}
+
+pub fn map<V: Encode>(
+    name: &str,
+    hasher: StorageHasher,
+    key: &str,
+    value: &str,
+    default: V,
+) -> StorageEntryMetadata {
+    StorageEntryMetadata {
+        name: string(name),
+        modifier: StorageEntryModifier::Default,
+        ty: StorageEntryType::Map {
+            hasher,
+            key: string(key),
+            value: string(value),
+            is_linked: false,
+        },
+        default: decoded(default.encode()),
+        documentation: strings(&[]),
+    }
+}
+
+pub fn double_map<V: Encode>(
+    name: &str,
+    hasher: StorageHasher,
+    key1: &str,
+    key2_hasher: StorageHasher,
+    key2: &str,
+    value: &str,
+    default: V,
+) -> StorageEntryMetadata {
+    StorageEntryMetadata {
+        name: string(name),
+        modifier: StorageEntryModifier::Default,
+        ty: StorageEntryType::DoubleMap {
+            hasher,
+            key1: string(key1),
+            key2: string(key2),
+            value: string(value),
+            key2_hasher,
+        },
+        default: decoded(default.encode()),
+        documentation: strings(&[]),
+    }
+}
+
+fn storage(prefix: &str, entries: Vec<StorageEntryMetadata>) -> StorageMetadata {
+    StorageMetadata {
+        prefix: string(prefix),
+        entries: decoded(entries),
+    }
+}
+
+/// A module with only storage, for entry kinds the node modules don't use.
+pub fn storage_module(name: &str, entries: Vec<StorageEntryMetadata>) -> ModuleMetadata {
+    ModuleMetadata {
+        name: string(name),
+        storage: Some(decoded(storage(name, entries))),
+        calls: None,
+        event: None,
+        constants: decoded(vec![]),
+        errors: decoded(vec![]),
+    }
+}
+
+/// A module with only events.
+pub fn event_module(name: &str, events: Vec<EventMetadata>) -> ModuleMetadata {
+    ModuleMetadata {
+        name: string(name),
+        storage: None,
+        calls: None,
+        event: Some(decoded(events)),
+        constants: decoded(vec![]),
+        errors: decoded(vec![]),
+    }
+}
+
+/// Modules of the node runtime, in runtime order.
+pub fn node_modules() -> Vec<ModuleMetadata> {
+    vec![
+        ModuleMetadata {
+            name: string("System"),
+            storage: Some(decoded(storage(
+                "System",
+                vec![
+                    map(
+                        "AccountNonce",
+                        StorageHasher::Blake2_256,
+                        "T::AccountId",
+                        "T::Index",
+                        0u32,
+                    ),
+                    plain("Number", "T::BlockNumber", 0u32),
+                    plain(
+                        "Events",
+                        "Vec<EventRecord<T::Event, T::Hash>>",
+                        Vec::<u8>::new(),
+                    ),
+                ],
+            ))),
+            calls: Some(decoded(vec![
+                call("remark", &[("_remark", "Vec<u8>")]),
+                call("set_code", &[("code", "Vec<u8>")]),
+            ])),
+            event: Some(decoded(vec![
+                event("ExtrinsicSuccess", &["DispatchInfo"]),
+                event("ExtrinsicFailed", &["DispatchError", "DispatchInfo"]),
+            ])),
+            constants: decoded(vec![]),
+            errors: decoded(vec![
+                error("InvalidSpecName", &[]),
+                error("SpecVersionNotAllowedToDecrease", &[]),
+            ]),
+        },
+        ModuleMetadata {
+            name: string("Balances"),
+            storage: Some(decoded(storage(
+                "Balances",
+                vec![
+                    plain("TotalIssuance", "T::Balance", 0u128),
+                    map(
+                        "FreeBalance",
+                        StorageHasher::Blake2_256,
+                        "T::AccountId",
+                        "T::Balance",
+                        0u128,
+                    ),
+                ],
+            ))),
+            calls: Some(decoded(vec![call(
+                "transfer",
+                &[
+                    ("dest", "<T::Lookup as StaticLookup>::Source"),
+                    ("value", "Compact<T::Balance>"),
+                ],
+            )])),
+            event: Some(decoded(vec![event(
+                "Transfer",
+                &["AccountId", "AccountId", "Balance", "Balance"],
+            )])),
+            constants: decoded(vec![
+                constant("ExistentialDeposit", "T::Balance", 500u128),
+                constant("TransferFee", "T::Balance", 10u128),
+            ]),
+            errors: decoded(vec![
+                error("InsufficientBalance", &[" Balance too low to send value"]),
+                error("ExistentialDeposit", &[" Value too low to create account"]),
+            ]),
+        },
+        ModuleMetadata {
+            name: string("Contracts"),
+            storage: Some(decoded(storage(
+                "Contracts",
+                vec![
+                    plain("AccountCounter", "u64", 0u64),
+                    map(
+                        "ContractInfoOf",
+                        StorageHasher::Twox64Concat,
+                        "T::AccountId",
+                        "ContractInfo<T>",
+                        Option::<()>::None,
+                    ),
+                ],
+            ))),
+            calls: Some(decoded(vec![
+                call(
+                    "put_code",
+                    &[("gas_limit", "Compact<Gas>"), ("code", "Vec<u8>")],
+                ),
+                call(
+                    "call",
+                    &[
+                        ("dest", "<T::Lookup as StaticLookup>::Source"),
+                        ("value", "BalanceOf<T>"),
+                        ("gas_limit", "Compact<Gas>"),
+                        ("data", "Vec<u8>"),
+                    ],
+                ),
+                call(
+                    "instantiate",
+                    &[
+                        ("endowment", "Compact<BalanceOf<T>>"),
+                        ("gas_limit", "Compact<Gas>"),
+                        ("code_hash", "CodeHash<T>"),
+                        ("data", "Vec<u8>"),
+                    ],
+                ),
+            ])),
+            event: Some(decoded(vec![
+                event("Instantiated", &["AccountId", "AccountId"]),
+                event("CodeStored", &["Hash"]),
+            ])),
+            constants: decoded(vec![]),
+            errors: decoded(vec![]),
+        },
+    ]
+}
+
+/// Signed extensions of the node runtime, in runtime order.
+pub fn node_signed_extensions() -> Vec<&'static str> {
+    vec![
+        "CheckVersion",
+        "CheckGenesis",
+        "CheckEra",
+        "CheckNonce",
+        "CheckWeight",
+        "ChargeTransactionPayment",
+        "CheckBlockGasLimit",
+    ]
+}
+
+/// V11 metadata for the node runtime.
+pub fn node_metadata() -> RuntimeMetadataPrefixed {
+    metadata(node_modules())
+}
+
+/// V11 metadata for the given modules, with the signed extensions of the node runtime.
+pub fn metadata(modules: Vec<ModuleMetadata>) -> RuntimeMetadataPrefixed {
+    metadata_with_signed_extensions(modules, node_signed_extensions())
+}
+
+/// V11 metadata for the given modules and signed extensions.
+pub fn metadata_with_signed_extensions(
+    modules: Vec<ModuleMetadata>,
+    signed_extensions: Vec<&str>,
+) -> RuntimeMetadataPrefixed {
+    RuntimeMetadataPrefixed(
+        META_RESERVED,
+        RuntimeMetadata::V11(RuntimeMetadataV11 {
+            modules: decoded(modules),
+            extrinsic: ExtrinsicMetadata {
+                version: 4,
+                signed_extensions: signed_extensions.into_iter().map(string).collect(),
+            },
+        }),
+    )
+}
+
+/// Encoded V11 metadata for the node runtime, as returned by `state_getMetadata`.
+pub fn node_metadata_bytes() -> Vec<u8> {
+    node_metadata().encode()
+}
+
+/// Encoded V10 metadata for the node runtime.
+pub fn node_metadata_v10_bytes() -> Vec<u8> {
+    (META_RESERVED, 10u8, v10::node_metadata()).encode()
+}
+
+/// Layout of metadata V10, which `frame_metadata` no longer provides.
+///
+/// `DecodeDifferent` values are encoded as the decoded value, so plain types are used in
+/// their place. V10 has no `Identity` storage hasher and no extrinsic metadata.
+mod v10 {
+    use codec::Encode;
+
+    #[derive(Encode)]
+    #[allow(dead_code)]
+    pub enum StorageHasher {
+        Blake2_128,
+        Blake2_256,
+        Blake2_128Concat,
+        Twox128,
+        Twox256,
+        Twox64Concat,
+    }
+
+    #[derive(Encode)]
+    #[allow(dead_code)]
+    pub enum StorageEntryModifier {
+        Optional,
+        Default,
+    }
+
+    #[derive(Encode)]
+    #[allow(dead_code)]
+    pub enum StorageEntryType {
+        Plain(String),
+        Map {
+            hasher: StorageHasher,
+            key: String,
+            value: String,
+            is_linked: bool,
+        },
+        DoubleMap {
+            hasher: StorageHasher,
+            key1: String,
+            key2: String,
+            value: String,
+            key2_hasher: StorageHasher,
+        },
+    }
+
+    #[derive(Encode)]
+    pub struct StorageEntryMetadata {
+        pub name: String,
+        pub modifier: StorageEntryModifier,
+        pub ty: StorageEntryType,
+        pub default: Vec<u8>,
+        pub documentation: Vec<String>,
+    }
+
+    #[derive(Encode)]
+    pub struct StorageMetadata {
+        pub prefix: String,
+        pub entries: Vec<StorageEntryMetadata>,
+    }
+
+    #[derive(Encode)]
+    pub struct FunctionArgumentMetadata {
+        pub name: String,
+        pub ty: String,
+    }
+
+    #[derive(Encode)]
+    pub struct FunctionMetadata {
+        pub name: String,
+        pub arguments: Vec<FunctionArgumentMetadata>,
+        pub documentation: Vec<String>,
+    }
+
+    #[derive(Encode)]
+    pub struct EventMetadata {
+        pub name: String,
+        pub arguments: Vec<String>,
+        pub documentation: Vec<String>,
+    }
+
+    #[derive(Encode)]
+    pub struct ModuleConstantMetadata {
+        pub name: String,
+        pub ty: String,
+        pub value: Vec<u8>,
+        pub documentation: Vec<String>,
+    }
+
+    #[derive(Encode)]
+    pub struct ErrorMetadata {
+        pub name: String,
+        pub documentation: Vec<String>,
+    }
+
+    #[derive(Encode)]
+    pub struct ModuleMetadata {
+        pub name: String,
+        pub storage: Option<StorageMetadata>,
+        pub calls: Option<Vec<FunctionMetadata>>,
+        pub event: Option<Vec<EventMetadata>>,
+        pub constants: Vec<ModuleConstantMetadata>,
+        pub errors: Vec<ErrorMetadata>,
+    }
+
+    #[derive(Encode)]
+    pub struct RuntimeMetadataV10 {
+        pub modules: Vec<ModuleMetadata>,
+    }
+
+    fn entry<V: Encode>(
+        name: &str,
+        ty: StorageEntryType,
+        default: V,
+    ) -> StorageEntryMetadata {
+        StorageEntryMetadata {
+            name: name.into(),
+            modifier: StorageEntryModifier::Default,
+            ty,
+            default: default.encode(),
+            documentation: vec![],
+        }
+    }
+
+    fn map(hasher: StorageHasher, key: &str, value: &str) -> StorageEntryType {
+        StorageEntryType::Map {
+            hasher,
+            key: key.into(),
+            value: value.into(),
+            is_linked: false,
+        }
+    }
+
+    fn call(name: &str, arguments: &[(&str, &str)]) -> FunctionMetadata {
+        FunctionMetadata {
+            name: name.into(),
+            arguments: arguments
+                .iter()
+                .map(|(name, ty)| {
+                    FunctionArgumentMetadata {
+                        name: name.to_string(),
+                        ty: ty.to_string(),
+                    }
+                })
+                .collect(),
+            documentation: vec![],
+        }
+    }
+
+    fn event(name: &str, arguments: &[&str]) -> EventMetadata {
+        EventMetadata {
+            name: name.into(),
+            arguments: arguments.iter().map(|arg| arg.to_string()).collect(),
+            documentation: vec![],
+        }
+    }
+
+    fn error(name: &str) -> ErrorMetadata {
+        ErrorMetadata {
+            name: name.into(),
+            documentation: vec![],
+        }
+    }
+
+    /// The node runtime modules of `super::node_modules`, in the V10 layout.
+    pub fn node_metadata() -> RuntimeMetadataV10 {
+        let system = ModuleMetadata {
+            name: "System".into(),
+            storage: Some(StorageMetadata {
+                prefix: "System".into(),
+                entries: vec![
+                    entry(
+                        "AccountNonce",
+                        map(StorageHasher::Blake2_256, "T::AccountId", "T::Index"),
+                        0u32,
+                    ),
+                    entry(
+                        "Number",
+                        StorageEntryType::Plain("T::BlockNumber".into()),
+                        0u32,
+                    ),
+                ],
+            }),
+            calls: Some(vec![
+                call("remark", &[("_remark", "Vec<u8>")]),
+                call("set_code", &[("code", "Vec<u8>")]),
+            ]),
+            event: Some(vec![
+                event("ExtrinsicSuccess", &["DispatchInfo"]),
+                event("ExtrinsicFailed", &["DispatchError", "DispatchInfo"]),
+            ]),
+            constants: vec![],
+            errors: vec![error("InvalidSpecName")],
+        };
+        let balances = ModuleMetadata {
+            name: "Balances".into(),
+            storage: Some(StorageMetadata {
+                prefix: "Balances".into(),
+                entries: vec![entry(
+                    "FreeBalance",
+                    map(StorageHasher::Blake2_256, "T::AccountId", "T::Balance"),
+                    0u128,
+                )],
+            }),
+            calls: Some(vec![call(
+                "transfer",
+                &[
+                    ("dest", "<T::Lookup as StaticLookup>::Source"),
+                    ("value", "Compact<T::Balance>"),
+                ],
+            )]),
+            event: Some(vec![event(
+                "Transfer",
+                &["AccountId", "AccountId", "Balance", "Balance"],
+            )]),
+            constants: vec![ModuleConstantMetadata {
+                name: "ExistentialDeposit".into(),
+                ty: "T::Balance".into(),
+                value: 500u128.encode(),
+                documentation: vec![],
+            }],
+            errors: vec![error("InsufficientBalance")],
+        };
+        let contracts = ModuleMetadata {
+            name: "Contracts".into(),
+            storage: Some(StorageMetadata {
+                prefix: "Contracts".into(),
+                entries: vec![entry(
+                    "ContractInfoOf",
+                    map(
+                        StorageHasher::Twox64Concat,
+                        "T::AccountId",
+                        "ContractInfo<T>",
+                    ),
+                    Option::<()>::None,
+                )],
+            }),
+            calls: Some(vec![call(
+                "put_code",
+                &[("gas_limit", "Compact<Gas>"), ("code", "Vec<u8>")],
+            )]),
+            event: Some(vec![
+                event("Instantiated", &["AccountId", "AccountId"]),
+                event("CodeStored", &["Hash"]),
+            ]),
+            constants: vec![],
+            errors: vec![],
+        };
+        RuntimeMetadataV10 {
+            modules: vec![system, balances, contracts],
+        }
+    }
+}
+
+/// Encoded metadata of the substrate `node_runtime` at the revision the client is built
+/// against, byte for byte what the node returns from `state_getMetadata`.
+pub fn node_runtime_metadata_bytes() -> Vec<u8> {
+    node_runtime::Runtime::metadata().encode()
+}
@@ -14,30 +14,38 @@
 // You should have received a copy of the GNU General Public License
 // along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
 
-// jsonrpsee subscriptions are interminable.
-// Allows `while let status = subscription.next().await {}`
-// Related: https://github.com/paritytech/substrate-subxt/issues/66
-#![allow(irrefutable_let_patterns)]
-
-use std::convert::TryInto;
+use std::{
+    marker::PhantomData,
+    sync::Arc,
+    time::Duration,
+};
 
 use codec::{
     Decode,
     Encode,
     Error as CodecError,
 };
-use jsonrpsee::{
-    client::Subscription,
-    core::common::{
-        to_value as to_json_value,
-        Params,
+use futures::{
+    future::{
+        self,
+        Either,
+        Future,
     },
-    Client,
+    stream::StreamExt,
+};
+use futures_timer::Delay;
+use jsonrpsee::core::common::{
+    to_value as to_json_value,
+    Params,
 };
 
 use num_traits::bounds::Bounded;
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize,
+};
 
-use frame_metadata::RuntimeMetadataPrefixed;
 use sp_core::{
     storage::{
         StorageChangeSet,
@@ -46,6 +54,7 @@ use sp_core::{
     },
     twox_128,
     Bytes,
+    Hasher,
 };
 use sp_rpc::{
     list::ListOrValue,
@@ -57,14 +66,19 @@ use sp_runtime::{
         SignedBlock,
     },
     traits::Hash,
+    DispatchError,
 };
 use sp_transaction_pool::TransactionStatus;
+use sp_trie::{
+    Layout,
+    StorageProof,
+};
 use sp_version::RuntimeVersion;
-use std::marker::PhantomData;
 
 use crate::{
     error::Error,
     events::{
+        EventRecord,
         EventsDecoder,
         RawEvent,
         RuntimeEvent,
@@ -76,29 +90,166 @@ use crate::{
             System,
             SystemEvent,
         },
+        Event,
     },
     metadata::Metadata,
 };
 
+mod mock;
+mod reconnect;
+mod transport;
+
+pub use self::{
+    mock::MockTransport,
+    reconnect::{
+        ReconnectPolicy,
+        ReconnectingTransport,
+    },
+    transport::{
+        HttpTransport,
+        Notifications,
+        RpcFuture,
+        Transport,
+        WsTransport,
+    },
+};
+
 pub type ChainBlock<T> =
     SignedBlock<Block<<T as System>::Header, <T as System>::Extrinsic>>;
 pub type BlockNumber<T> = NumberOrHex<<T as System>::BlockNumber>;
 
+/// Identifies a child trie in the child storage RPCs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChildTrie {
+    /// Key under which the child trie root is stored, starting with
+    /// `:child_storage:default:`.
+    pub storage_key: StorageKey,
+    /// Unique id of the child trie.
+    pub unique_id: StorageKey,
+    /// Type of the child trie, `1` for tries with a cryptographically unique id.
+    pub child_type: u32,
+}
+
+/// Subscription to notifications pushed by the node.
+pub struct Subscription<T> {
+    notifications: Notifications,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Subscription<T> {
+    /// Returns the next notification, or `None` once the subscription is closed.
+    ///
+    /// An `Err` item does not close the subscription. In particular a reconnecting
+    /// transport yields `Error::SubscriptionGap` and then carries on.
+    pub async fn next(&mut self) -> Option<Result<T, Error>> {
+        let notification = self.notifications.next().await?;
+        Some(notification.and_then(|value| Ok(serde_json::from_value(value)?)))
+    }
+}
+
 /// Client for substrate rpc interfaces
 #[derive(Clone)]
 pub struct Rpc<T: System> {
-    client: Client,
-    marker: std::marker::PhantomData<T>,
+    transport: Arc<dyn Transport>,
+    timeout: Option<Duration>,
+    watch_timeout: Option<Duration>,
+    marker: PhantomData<T>,
+}
+
+/// Fails with `Error::Timeout` if `future` does not complete within `timeout`.
+async fn with_timeout<R, F>(timeout: Option<Duration>, future: F) -> Result<R, Error>
+where
+    F: Future<Output = Result<R, Error>>,
+{
+    match timeout {
+        Some(timeout) => {
+            match future::select(Box::pin(future), Delay::new(timeout)).await {
+                Either::Left((result, _)) => result,
+                Either::Right(_) => Err(Error::Timeout(timeout)),
+            }
+        }
+        None => future.await,
+    }
 }
 
 impl<T> Rpc<T>
 where
     T: System,
 {
+    /// Creates a client on top of the given transport.
+    pub fn new<R: Transport + 'static>(transport: R) -> Self {
+        Rpc {
+            transport: Arc::new(transport),
+            timeout: None,
+            watch_timeout: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the timeout applied to every call, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Sets the timeout applied to every call, `None` waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the timeout for a watched extrinsic to be included in a block, if any.
+    pub fn watch_timeout(&self) -> Option<Duration> {
+        self.watch_timeout
+    }
+
+    /// Sets the timeout for a watched extrinsic to be included in a block, `None` waits
+    /// forever.
+    pub fn set_watch_timeout(&mut self, timeout: Option<Duration>) {
+        self.watch_timeout = timeout;
+    }
+
+    /// Connects to `url`, selecting the transport from the url scheme.
+    ///
+    /// `ws://` and `wss://` use a websocket, `http://` and `https://` plain HTTP.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        match url::Url::parse(url)?.scheme() {
+            "ws" | "wss" => Self::connect_ws(url).await,
+            "http" | "https" => Ok(Self::connect_http(url)),
+            scheme => Err(Error::UnsupportedScheme(scheme.to_string())),
+        }
+    }
+
+    /// Connects to a websocket endpoint.
     pub async fn connect_ws(url: &str) -> Result<Self, Error> {
-        let raw_client = jsonrpsee::ws::ws_raw_client(&url).await?;
-        Ok(Rpc {
-            client: raw_client.into(),
+        Ok(Self::new(WsTransport::connect(url).await?))
+    }
+
+    /// Creates a client for an HTTP endpoint. Subscriptions are not available.
+    pub fn connect_http(url: &str) -> Self {
+        Self::new(HttpTransport::new(url))
+    }
+
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: &'static str,
+        params: Params,
+    ) -> Result<R, Error> {
+        let request = self.transport.request(method, params);
+        let value = with_timeout(self.timeout, request).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    async fn subscribe<R: DeserializeOwned>(
+        &self,
+        subscribe_method: &'static str,
+        params: Params,
+        unsubscribe_method: &'static str,
+    ) -> Result<Subscription<R>, Error> {
+        let subscribe =
+            self.transport
+                .subscribe(subscribe_method, params, unsubscribe_method);
+        let notifications = with_timeout(self.timeout, subscribe).await?;
+        Ok(Subscription {
+            notifications,
             marker: PhantomData,
         })
     }
@@ -112,8 +263,7 @@ where
         // todo: update jsonrpsee::rpc_api! macro to accept shared Client (currently only RawClient)
         // until then we manually construct params here and in other methods
         let params = Params::Array(vec![to_json_value(key)?, to_json_value(hash)?]);
-        let data: Option<StorageData> =
-            self.client.request("state_getStorage", params).await?;
+        let data: Option<StorageData> = self.request("state_getStorage", params).await?;
         match data {
             Some(data) => {
                 let value = Decode::decode(&mut &data.0[..])?;
@@ -123,6 +273,78 @@ where
         }
     }
 
+    /// Fetch the raw value of a child storage key
+    pub async fn child_storage(
+        &self,
+        child: &ChildTrie,
+        key: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<StorageData>, Error> {
+        let params = Params::Array(vec![
+            to_json_value(&child.storage_key)?,
+            to_json_value(&child.unique_id)?,
+            to_json_value(child.child_type)?,
+            to_json_value(key)?,
+            to_json_value(hash)?,
+        ]);
+        self.request("state_getChildStorage", params).await
+    }
+
+    /// Fetch the keys of a child trie with the given prefix
+    pub async fn child_storage_keys(
+        &self,
+        child: &ChildTrie,
+        prefix: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, Error> {
+        let params = Params::Array(vec![
+            to_json_value(&child.storage_key)?,
+            to_json_value(&child.unique_id)?,
+            to_json_value(child.child_type)?,
+            to_json_value(prefix)?,
+            to_json_value(hash)?,
+        ]);
+        self.request("state_getChildKeys", params).await
+    }
+
+    /// Fetch the keys with the given prefix
+    pub async fn storage_keys(
+        &self,
+        prefix: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, Error> {
+        let params = Params::Array(vec![to_json_value(prefix)?, to_json_value(hash)?]);
+        self.request("state_getKeys", params).await
+    }
+
+    /// Fetch up to `count` keys with the given prefix, in order, starting after
+    /// `start_key`
+    pub async fn storage_keys_paged(
+        &self,
+        prefix: Option<StorageKey>,
+        count: u32,
+        start_key: Option<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, Error> {
+        let params = Params::Array(vec![
+            to_json_value(prefix)?,
+            to_json_value(count)?,
+            to_json_value(start_key)?,
+            to_json_value(hash)?,
+        ]);
+        self.request("state_getKeysPaged", params).await
+    }
+
+    /// Fetch a proof of the storage entries at `keys`
+    pub async fn read_proof(
+        &self,
+        keys: Vec<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<ReadProof<T::Hash>, Error> {
+        let params = Params::Array(vec![to_json_value(keys)?, to_json_value(hash)?]);
+        self.request("state_getReadProof", params).await
+    }
+
     /// Query historical storage entries
     pub async fn query_storage(
         &self,
@@ -130,8 +352,12 @@ where
         from: T::Hash,
         to: Option<T::Hash>,
     ) -> Result<Vec<StorageChangeSet<<T as System>::Hash>>, Error> {
-        let params = Params::Array(vec![to_json_value(keys)?, to_json_value(from)?, to_json_value(to)?]);
-        self.client.request("state_queryStorage", params).await.map_err(Into::into)
+        let params = Params::Array(vec![
+            to_json_value(keys)?,
+            to_json_value(from)?,
+            to_json_value(to)?,
+        ]);
+        self.request("state_queryStorage", params).await
     }
 
     /// Fetch the genesis hash
@@ -141,7 +367,7 @@ where
         )));
         let params = Params::Array(vec![to_json_value(block_zero)?]);
         let list_or_value: ListOrValue<Option<T::Hash>> =
-            self.client.request("chain_getBlockHash", params).await?;
+            self.request("chain_getBlockHash", params).await?;
         match list_or_value {
             ListOrValue::Value(genesis_hash) => {
                 genesis_hash.ok_or_else(|| "Genesis hash not found".into())
@@ -150,15 +376,10 @@ where
         }
     }
 
-    /// Fetch the metadata
-    pub async fn metadata(&self) -> Result<Metadata, Error> {
-        let bytes: Bytes = self
-            .client
-            .request("state_getMetadata", Params::None)
-            .await?;
-        let meta: RuntimeMetadataPrefixed = Decode::decode(&mut &bytes[..])?;
-        let metadata: Metadata = meta.try_into()?;
-        Ok(metadata)
+    /// Fetch the encoded metadata
+    pub async fn metadata_bytes(&self) -> Result<Bytes, Error> {
+        let bytes = self.request("state_getMetadata", Params::None).await?;
+        Ok(bytes)
     }
 
     /// Get a header
@@ -167,7 +388,7 @@ where
         hash: Option<T::Hash>,
     ) -> Result<Option<T::Header>, Error> {
         let params = Params::Array(vec![to_json_value(hash)?]);
-        let header = self.client.request("chain_getHeader", params).await?;
+        let header = self.request("chain_getHeader", params).await?;
         Ok(header)
     }
 
@@ -178,7 +399,7 @@ where
     ) -> Result<Option<T::Hash>, Error> {
         let block_number = block_number.map(|bn| ListOrValue::Value(bn));
         let params = Params::Array(vec![to_json_value(block_number)?]);
-        let list_or_value = self.client.request("chain_getBlockHash", params).await?;
+        let list_or_value = self.request("chain_getBlockHash", params).await?;
         match list_or_value {
             ListOrValue::Value(hash) => Ok(hash),
             ListOrValue::List(_) => Err("Expected a Value, got a List".into()),
@@ -187,10 +408,7 @@ where
 
     /// Get a block hash of the latest finalized block
     pub async fn finalized_head(&self) -> Result<T::Hash, Error> {
-        let hash = self
-            .client
-            .request("chain_getFinalizedHead", Params::None)
-            .await?;
+        let hash = self.request("chain_getFinalizedHead", Params::None).await?;
         Ok(hash)
     }
 
@@ -200,7 +418,7 @@ where
         hash: Option<T::Hash>,
     ) -> Result<Option<ChainBlock<T>>, Error> {
         let params = Params::Array(vec![to_json_value(hash)?]);
-        let block = self.client.request("chain_getBlock", params).await?;
+        let block = self.request("chain_getBlock", params).await?;
         Ok(block)
     }
 
@@ -210,37 +428,55 @@ where
         at: Option<T::Hash>,
     ) -> Result<RuntimeVersion, Error> {
         let params = Params::Array(vec![to_json_value(at)?]);
-        let version = self
-            .client
-            .request("state_getRuntimeVersion", params)
-            .await?;
+        let version = self.request("state_getRuntimeVersion", params).await?;
         Ok(version)
     }
 }
 
+/// Storage key of the System Events of a block.
+fn events_storage_key() -> Vec<u8> {
+    let mut storage_key = twox_128(b"System").to_vec();
+    storage_key.extend(twox_128(b"Events").to_vec());
+    storage_key
+}
+
 impl<T: System + Balances + 'static> Rpc<T> {
     /// Subscribe to substrate System Events
     pub async fn subscribe_events(
         &self,
     ) -> Result<Subscription<StorageChangeSet<<T as System>::Hash>>, Error> {
-        let mut storage_key = twox_128(b"System").to_vec();
-        storage_key.extend(twox_128(b"Events").to_vec());
+        let storage_key = events_storage_key();
         log::debug!("Events storage key {:?}", hex::encode(&storage_key));
 
         let keys = Some(vec![StorageKey(storage_key)]);
         let params = Params::Array(vec![to_json_value(keys)?]);
 
         let subscription = self
-            .client
             .subscribe("state_subscribeStorage", params, "state_unsubscribeStorage")
             .await?;
         Ok(subscription)
     }
 
+    /// Fetch and decode the System Events of the block with the given hash
+    pub async fn events_at(
+        &self,
+        decoder: &EventsDecoder<T>,
+        block_hash: T::Hash,
+    ) -> Result<Vec<EventRecord<T>>, Error> {
+        let params = Params::Array(vec![
+            to_json_value(StorageKey(events_storage_key()))?,
+            to_json_value(Some(block_hash))?,
+        ]);
+        let data: Option<StorageData> = self.request("state_getStorage", params).await?;
+        match data {
+            Some(data) => Ok(decoder.decode_events(&mut &data.0[..])?),
+            None => Ok(Vec::new()),
+        }
+    }
+
     /// Subscribe to blocks.
     pub async fn subscribe_blocks(&self) -> Result<Subscription<T::Header>, Error> {
         let subscription = self
-            .client
             .subscribe(
                 "chain_subscribeNewHeads",
                 Params::None,
@@ -256,7 +492,6 @@ impl<T: System + Balances + 'static> Rpc<T> {
         &self,
     ) -> Result<Subscription<T::Header>, Error> {
         let subscription = self
-            .client
             .subscribe(
                 "chain_subscribeFinalizedHeads",
                 Params::None,
@@ -273,13 +508,18 @@ impl<T: System + Balances + 'static> Rpc<T> {
     ) -> Result<T::Hash, Error> {
         let bytes: Bytes = extrinsic.encode().into();
         let params = Params::Array(vec![to_json_value(bytes)?]);
-        let xt_hash = self
-            .client
-            .request("author_submitExtrinsic", params)
-            .await?;
+        let xt_hash = self.request("author_submitExtrinsic", params).await?;
         Ok(xt_hash)
     }
 
+    /// Fetch the extrinsics waiting in the transaction pool
+    pub async fn pending_extrinsics(&self) -> Result<Vec<Bytes>, Error> {
+        let extrinsics = self
+            .request("author_pendingExtrinsics", Params::None)
+            .await?;
+        Ok(extrinsics)
+    }
+
     pub async fn watch_extrinsic<E: Encode>(
         &self,
         extrinsic: E,
@@ -287,7 +527,6 @@ impl<T: System + Balances + 'static> Rpc<T> {
         let bytes: Bytes = extrinsic.encode().into();
         let params = Params::Array(vec![to_json_value(bytes)?]);
         let subscription = self
-            .client
             .subscribe(
                 "author_submitAndWatchExtrinsic",
                 params,
@@ -298,18 +537,30 @@ impl<T: System + Balances + 'static> Rpc<T> {
     }
 
     /// Create and submit an extrinsic and return corresponding Event if successful
+    ///
+    /// The watch timeout covers the whole wait for the extrinsic to be included in a
+    /// block, while the call timeout applies to each call made along the way.
     pub async fn submit_and_watch_extrinsic<E: Encode + 'static>(
         self,
         extrinsic: E,
         decoder: EventsDecoder<T>,
+    ) -> Result<ExtrinsicSuccess<T>, Error> {
+        let timeout = self.watch_timeout;
+        with_timeout(timeout, self.watch_extrinsic_events(extrinsic, decoder)).await
+    }
+
+    async fn watch_extrinsic_events<E: Encode + 'static>(
+        self,
+        extrinsic: E,
+        decoder: EventsDecoder<T>,
     ) -> Result<ExtrinsicSuccess<T>, Error> {
         let ext_hash = T::Hashing::hash_of(&extrinsic);
         log::info!("Submitting Extrinsic `{:?}`", ext_hash);
 
-        let events_sub = self.subscribe_events().await?;
         let mut xt_sub = self.watch_extrinsic(extrinsic).await?;
 
-        while let status = xt_sub.next().await {
+        while let Some(status) = xt_sub.next().await {
+            let status = status?;
             log::info!("received status {:?}", status);
             match status {
                 // ignore in progress extrinsic for now
@@ -326,14 +577,14 @@ impl<T: System + Balances + 'static> Rpc<T> {
                                 block_hash,
                                 signed_block.block.extrinsics.len()
                             );
-                            wait_for_block_events(
-                                decoder,
+                            let events = self.events_at(&decoder, block_hash).await?;
+                            extrinsic_success(
+                                decoder.metadata(),
                                 ext_hash,
                                 signed_block,
                                 block_hash,
-                                events_sub,
+                                events,
                             )
-                            .await
                         }
                         None => {
                             Err(format!("Failed to find block {:?}", block_hash).into())
@@ -345,7 +596,7 @@ impl<T: System + Balances + 'static> Rpc<T> {
                 TransactionStatus::Invalid => return Err("Extrinsic Invalid".into()),
             }
         }
-        unreachable!()
+        Err("Extrinsic subscription closed".into())
     }
 }
 
@@ -356,16 +607,16 @@ pub struct ExtrinsicSuccess<T: System> {
     pub block: T::Hash,
     /// Extrinsic hash.
     pub extrinsic: T::Hash,
-    /// Raw runtime events, can be decoded by the caller.
-    pub events: Vec<RuntimeEvent>,
+    /// Records of the raw runtime events, can be decoded by the caller.
+    pub events: Vec<EventRecord<T>>,
 }
 
 impl<T: System> ExtrinsicSuccess<T> {
     /// Find the Event for the given module/variant, with raw encoded event data.
     /// Returns `None` if the Event is not found.
     pub fn find_event_raw(&self, module: &str, variant: &str) -> Option<&RawEvent> {
-        self.events.iter().find_map(|evt| {
-            match evt {
+        self.events.iter().find_map(|record| {
+            match record.event {
                 RuntimeEvent::Raw(ref raw)
                     if raw.module == module && raw.variant == variant =>
                 {
@@ -380,35 +631,61 @@ impl<T: System> ExtrinsicSuccess<T> {
     pub fn system_events(&self) -> Vec<&SystemEvent> {
         self.events
             .iter()
-            .filter_map(|evt| {
-                match evt {
-                    RuntimeEvent::System(evt) => Some(evt),
+            .filter_map(|record| {
+                match record.event {
+                    RuntimeEvent::System(ref evt) => Some(evt),
                     _ => None,
                 }
             })
             .collect()
     }
 
-    /// Find the Event for the given module/variant, attempting to decode the event data.
+    /// Find the Event `E`, attempting to decode the event data.
     /// Returns `None` if the Event is not found.
     /// Returns `Err` if the data fails to decode into the supplied type
-    pub fn find_event<E: Decode>(
-        &self,
-        module: &str,
-        variant: &str,
-    ) -> Option<Result<E, CodecError>> {
-        self.find_event_raw(module, variant)
+    pub fn find_event<E: Event>(&self) -> Option<Result<E, CodecError>> {
+        self.find_event_raw(E::MODULE, E::EVENT)
             .map(|evt| E::decode(&mut &evt.data[..]))
     }
 }
 
-/// Waits for events for the block triggered by the extrinsic
-pub async fn wait_for_block_events<T: System + Balances + 'static>(
-    decoder: EventsDecoder<T>,
+/// Proof of storage entries, as returned by `state_getReadProof`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadProof<Hash> {
+    /// Block hash the proof was generated at.
+    pub at: Hash,
+    /// Trie nodes proving the storage entries.
+    pub proof: Vec<Bytes>,
+}
+
+impl<Hash> ReadProof<Hash> {
+    /// Reads the value at `key` from the proof, checking it against `state_root`.
+    ///
+    /// Returns `Ok(None)` if the proof shows there is no value at `key`, and an error if the
+    /// proof does not match the root or is missing the nodes for `key`.
+    pub fn verify<H: Hasher<Out = Hash>>(
+        &self,
+        state_root: &Hash,
+        key: &StorageKey,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let nodes = self.proof.iter().map(|node| node.0.clone()).collect();
+        let db = StorageProof::new(nodes).into_memory_db::<H>();
+        sp_trie::read_trie_value::<Layout<H>, _>(&db, state_root, &key.0)
+            .map_err(|err| Error::InvalidProof(format!("{:?}", err)))
+    }
+}
+
+/// Collects the events of the extrinsic with hash `ext_hash` from the events of its block.
+///
+/// Fails with [`Error::Runtime`](../enum.Error.html#variant.Runtime) if the extrinsic was
+/// included but its dispatch failed.
+pub fn extrinsic_success<T: System + Balances + 'static>(
+    metadata: &Metadata,
     ext_hash: T::Hash,
     signed_block: ChainBlock<T>,
     block_hash: T::Hash,
-    events_subscription: Subscription<StorageChangeSet<T::Hash>>,
+    block_events: Vec<EventRecord<T>>,
 ) -> Result<ExtrinsicSuccess<T>, Error> {
     let ext_index = signed_block
         .block
@@ -422,38 +699,234 @@ pub async fn wait_for_block_events<T: System + Balances + 'static>(
             Error::Other(format!("Failed to find Extrinsic with hash {:?}", ext_hash))
         })?;
 
-    let mut subscription = events_subscription;
-    while let change_set = subscription.next().await {
-        // only interested in events for the given block
-        if change_set.block != block_hash {
-            continue
+    let events = block_events
+        .into_iter()
+        .filter(|record| {
+            match record.phase {
+                Phase::ApplyExtrinsic(i) => i as usize == ext_index,
+                _ => false,
+            }
+        })
+        .collect::<Vec<_>>();
+    for record in &events {
+        if let RuntimeEvent::System(SystemEvent::ExtrinsicFailed(error, _)) =
+            &record.event
+        {
+            return Err(dispatch_error(metadata, error))
         }
-        let mut events = Vec::new();
-        for (_key, data) in change_set.changes {
-            if let Some(data) = data {
-                match decoder.decode_events(&mut &data.0[..]) {
-                    Ok(raw_events) => {
-                        for (phase, event) in raw_events {
-                            if let Phase::ApplyExtrinsic(i) = phase {
-                                if i as usize == ext_index {
-                                    events.push(event)
-                                }
-                            }
-                        }
+    }
+    if events.len() > 0 {
+        Ok(ExtrinsicSuccess {
+            block: block_hash,
+            extrinsic: ext_hash,
+            events,
+        })
+    } else {
+        Err(format!("No events found for block {}", block_hash).into())
+    }
+}
+
+/// Names a failed dispatch using the errors the runtime declares in its metadata.
+fn dispatch_error(metadata: &Metadata, error: &DispatchError) -> Error {
+    match error {
+        DispatchError::Module { index, error, .. } => {
+            let module_error = metadata
+                .module_with_errors(*index)
+                .and_then(|module| Ok((module.name(), module.error(*error)?)));
+            match module_error {
+                Ok((module, error)) => {
+                    Error::Runtime {
+                        module: module.to_string(),
+                        error: error.name().to_string(),
+                        docs: error.documentation().to_vec(),
                     }
-                    Err(err) => return Err(err.into()),
                 }
+                Err(err) => err.into(),
             }
         }
-        return if events.len() > 0 {
-            Ok(ExtrinsicSuccess {
-                block: block_hash,
-                extrinsic: ext_hash,
-                events,
+        DispatchError::BadOrigin => "Dispatch failed: bad origin".into(),
+        DispatchError::CannotLookup => "Dispatch failed: cannot lookup".into(),
+        DispatchError::Other(message) => format!("Dispatch failed: {}", message).into(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::convert::TryFrom;
+
+    use sp_core::H256;
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_trie::{
+        trie_types::TrieDBMut,
+        MemoryDB,
+        TrieMut,
+    };
+
+    use super::*;
+    use crate::{
+        metadata::fixtures,
+        DefaultNodeRuntime as Runtime,
+    };
+
+    /// Transport for a node which never answers.
+    struct Unresponsive;
+
+    impl Transport for Unresponsive {
+        fn request(&self, _: &'static str, _: Params) -> RpcFuture<serde_json::Value> {
+            Box::pin(future::pending())
+        }
+
+        fn subscribe(
+            &self,
+            _: &'static str,
+            _: Params,
+            _: &'static str,
+        ) -> RpcFuture<Notifications> {
+            Box::pin(future::pending())
+        }
+    }
+
+    /// Transport delaying every subscription notification of a `MockTransport`.
+    struct Slow(MockTransport, Duration);
+
+    impl Transport for Slow {
+        fn request(
+            &self,
+            method: &'static str,
+            params: Params,
+        ) -> RpcFuture<serde_json::Value> {
+            self.0.request(method, params)
+        }
+
+        fn subscribe(
+            &self,
+            subscribe_method: &'static str,
+            params: Params,
+            unsubscribe_method: &'static str,
+        ) -> RpcFuture<Notifications> {
+            let delay = self.1;
+            let subscribe =
+                self.0
+                    .subscribe(subscribe_method, params, unsubscribe_method);
+            Box::pin(async move {
+                let notifications = subscribe.await?.then(move |notification| {
+                    async move {
+                        Delay::new(delay).await;
+                        notification
+                    }
+                });
+                Ok(Box::pin(notifications) as Notifications)
             })
-        } else {
-            Err(format!("No events found for block {}", block_hash).into())
         }
     }
-    unreachable!()
+
+    /// Builds a trie of `entries`, returning its root and all of its nodes as a proof.
+    pub(crate) fn trie_proof(entries: &[(&[u8], &[u8])]) -> (H256, Vec<Bytes>) {
+        let mut db = MemoryDB::<BlakeTwo256>::default();
+        let mut root = H256::default();
+        {
+            let mut trie = TrieDBMut::new(&mut db, &mut root);
+            for (key, value) in entries {
+                trie.insert(key, value).unwrap();
+            }
+        }
+        let proof = db
+            .drain()
+            .into_iter()
+            .map(|(_, (node, _))| Bytes(node))
+            .collect();
+        (root, proof)
+    }
+
+    fn is_timeout<R>(result: Result<R, Error>, expected: Duration) -> bool {
+        match result {
+            Err(Error::Timeout(timeout)) => timeout == expected,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        let timeout = Duration::from_millis(10);
+        let mut rpc = Rpc::<Runtime>::new(Unresponsive);
+        rpc.set_timeout(Some(timeout));
+        let result = async_std::task::block_on(rpc.block_hash(None));
+
+        assert!(is_timeout(result, timeout));
+    }
+
+    #[test]
+    fn test_subscribe_timeout() {
+        let timeout = Duration::from_millis(10);
+        let mut rpc = Rpc::<Runtime>::new(Unresponsive);
+        rpc.set_timeout(Some(timeout));
+        let result = async_std::task::block_on(rpc.subscribe_blocks());
+
+        assert!(is_timeout(result, timeout));
+    }
+
+    #[test]
+    fn test_watch_timeout() {
+        let watch = |timeout, watch_timeout| {
+            let transport = MockTransport::new();
+            transport.notify(
+                "author_submitAndWatchExtrinsic",
+                vec![
+                    TransactionStatus::<H256, H256>::Ready,
+                    TransactionStatus::Dropped,
+                ],
+            );
+            let mut rpc = Rpc::<Runtime>::new(Slow(transport, Duration::from_millis(20)));
+            rpc.set_timeout(Some(timeout));
+            rpc.set_watch_timeout(watch_timeout);
+            let metadata = Metadata::decode(&fixtures::node_metadata_bytes()).unwrap();
+            let decoder = EventsDecoder::try_from(metadata).unwrap();
+            async_std::task::block_on(rpc.submit_and_watch_extrinsic(vec![0u8], decoder))
+        };
+        let timeout = Duration::from_millis(5);
+
+        // the call timeout does not limit the wait for the extrinsic
+        assert!(match watch(timeout, None) {
+            Err(Error::Other(message)) => message == "Extrinsic Dropped",
+            _ => false,
+        });
+        assert!(is_timeout(watch(timeout, Some(timeout)), timeout));
+    }
+
+    #[test]
+    fn test_read_proof_verify() {
+        let (root, proof) = trie_proof(&[
+            (&b"key1"[..], &b"value1"[..]),
+            (&b"key2"[..], &b"value2"[..]),
+        ]);
+        let proof = ReadProof {
+            at: H256::repeat_byte(1),
+            proof,
+        };
+        let key = |key: &[u8]| StorageKey(key.to_vec());
+
+        assert_eq!(
+            proof.verify::<BlakeTwo256>(&root, &key(b"key1")).unwrap(),
+            Some(b"value1".to_vec())
+        );
+        assert_eq!(
+            proof.verify::<BlakeTwo256>(&root, &key(b"key3")).unwrap(),
+            None
+        );
+        assert!(
+            match proof.verify::<BlakeTwo256>(&H256::repeat_byte(9), &key(b"key1")) {
+                Err(Error::InvalidProof(_)) => true,
+                _ => false,
+            }
+        );
+
+        let empty = ReadProof {
+            at: H256::repeat_byte(1),
+            proof: vec![],
+        };
+        assert!(match empty.verify::<BlakeTwo256>(&root, &key(b"key1")) {
+            Err(Error::InvalidProof(_)) => true,
+            _ => false,
+        });
+    }
 }
@@ -0,0 +1,153 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! In-process transport serving scripted responses, for testing without a node.
+
+use std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use futures::{
+    future,
+    stream,
+};
+use jsonrpsee::core::common::Params;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    error::Error,
+    rpc::transport::{
+        Notifications,
+        RpcFuture,
+        Transport,
+    },
+};
+
+#[derive(Default)]
+struct MockState {
+    responses: HashMap<String, VecDeque<JsonValue>>,
+    subscriptions: HashMap<String, VecDeque<Vec<JsonValue>>>,
+    requests: Vec<(String, JsonValue)>,
+}
+
+/// Transport answering requests and subscriptions from a script instead of a node.
+///
+/// Clones share the same script, so a test can keep a handle to add responses after
+/// passing the transport to a `ClientBuilder`.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTransport {
+    /// Creates a transport with an empty script.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Queues `result` as a response to `method`.
+    ///
+    /// Responses are returned in the order they were queued, the last one is repeated for
+    /// any further calls.
+    pub fn respond<R: Serialize>(&self, method: &str, result: R) -> &Self {
+        let result = serde_json::to_value(result).expect("Mock response must serialize");
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .entry(method.to_string())
+            .or_default()
+            .push_back(result);
+        self
+    }
+
+    /// Queues the notifications for the next subscription opened with `subscribe_method`.
+    ///
+    /// The subscription is closed after the last notification.
+    pub fn notify<R: Serialize>(
+        &self,
+        subscribe_method: &str,
+        notifications: Vec<R>,
+    ) -> &Self {
+        let notifications = notifications
+            .into_iter()
+            .map(|n| serde_json::to_value(n).expect("Mock notification must serialize"))
+            .collect();
+        self.state
+            .lock()
+            .unwrap()
+            .subscriptions
+            .entry(subscribe_method.to_string())
+            .or_default()
+            .push_back(notifications);
+        self
+    }
+
+    /// Returns the method and params of every call made so far.
+    pub fn requests(&self) -> Vec<(String, JsonValue)> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    fn record(state: &mut MockState, method: &str, params: &Params) {
+        let params = serde_json::to_value(params).unwrap_or(JsonValue::Null);
+        state.requests.push((method.to_string(), params));
+    }
+}
+
+impl Transport for MockTransport {
+    fn request(&self, method: &'static str, params: Params) -> RpcFuture<JsonValue> {
+        let mut state = self.state.lock().unwrap();
+        Self::record(&mut state, method, &params);
+        let response = state.responses.get_mut(method).and_then(|queue| {
+            if queue.len() > 1 {
+                queue.pop_front()
+            } else {
+                queue.front().cloned()
+            }
+        });
+        Box::pin(future::ready(response.ok_or_else(|| {
+            Error::Other(format!("No mock response for `{}`", method))
+        })))
+    }
+
+    fn subscribe(
+        &self,
+        subscribe_method: &'static str,
+        params: Params,
+        _unsubscribe_method: &'static str,
+    ) -> RpcFuture<Notifications> {
+        let mut state = self.state.lock().unwrap();
+        Self::record(&mut state, subscribe_method, &params);
+        let notifications = state
+            .subscriptions
+            .get_mut(subscribe_method)
+            .and_then(VecDeque::pop_front)
+            .map(|notifications| {
+                Box::pin(stream::iter(notifications.into_iter().map(Ok))) as Notifications
+            });
+        Box::pin(future::ready(notifications.ok_or_else(|| {
+            Error::Other(format!("No mock subscription for `{}`", subscribe_method))
+        })))
+    }
+}
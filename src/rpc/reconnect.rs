@@ -0,0 +1,557 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transport which re-opens its connection when the node goes away.
+
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::{
+    future::{
+        self,
+        Either,
+    },
+    lock::Mutex,
+    stream::{
+        self,
+        StreamExt,
+    },
+};
+use futures_timer::Delay;
+use jsonrpsee::core::common::Params;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    error::Error,
+    rpc::transport::{
+        Notifications,
+        RpcFuture,
+        Transport,
+        WsTransport,
+    },
+};
+
+/// Opens a new connection to the node.
+pub type Connect = Box<dyn Fn() -> RpcFuture<Arc<dyn Transport>> + Send + Sync>;
+
+/// When and how often a `ReconnectingTransport` tries to re-open its connection.
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    /// Delay before retrying after the first failed attempt.
+    pub initial_delay: Duration,
+    /// Upper bound of the delay, which doubles after every failed attempt.
+    pub max_delay: Duration,
+    /// Subscriptions give up after this many failed attempts, retry forever if `None`.
+    pub max_attempts: Option<u32>,
+    /// How long a subscription may stay idle before the connection is checked.
+    pub health_check_interval: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_attempts: None,
+            health_check_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+struct Connection {
+    transport: Arc<dyn Transport>,
+    /// Incremented every time the connection is re-opened.
+    generation: u64,
+}
+
+struct Shared {
+    connect: Connect,
+    policy: ReconnectPolicy,
+    connection: Mutex<Connection>,
+}
+
+impl Shared {
+    async fn current(&self) -> (Arc<dyn Transport>, u64) {
+        let connection = self.connection.lock().await;
+        (connection.transport.clone(), connection.generation)
+    }
+
+    /// Checks that connection `generation` is still the current one and responds.
+    async fn is_alive(&self, generation: u64) -> bool {
+        let (transport, current) = self.current().await;
+        if current != generation {
+            return false
+        }
+        let health = transport.request("system_health", Params::None);
+        let timeout = Delay::new(self.policy.health_check_interval);
+        match future::select(health, timeout).await {
+            Either::Left((result, _)) => result.is_ok(),
+            Either::Right(_) => false,
+        }
+    }
+
+    /// Makes one attempt to replace connection `generation`, unless that already happened
+    /// in the meantime.
+    async fn try_reconnect(
+        &self,
+        generation: u64,
+    ) -> Result<(Arc<dyn Transport>, u64), Error> {
+        let mut connection = self.connection.lock().await;
+        if connection.generation == generation {
+            connection.transport = (self.connect)().await?;
+            connection.generation += 1;
+        }
+        Ok((connection.transport.clone(), connection.generation))
+    }
+
+    /// Replaces connection `generation`, retrying with the backoff of the policy.
+    ///
+    /// The connection is only locked during an attempt, so requests fail rather than wait
+    /// while the backoff runs.
+    async fn reconnect(
+        &self,
+        generation: u64,
+    ) -> Result<(Arc<dyn Transport>, u64), Error> {
+        let mut delay = self.policy.initial_delay;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            log::info!("Reconnecting, attempt {}", attempts);
+            match self.try_reconnect(generation).await {
+                Ok(connection) => return Ok(connection),
+                Err(err) => {
+                    log::warn!("Reconnection attempt {} failed: {}", attempts, err);
+                    if self
+                        .policy
+                        .max_attempts
+                        .map_or(false, |max| attempts >= max)
+                    {
+                        return Err(err)
+                    }
+                }
+            }
+            Delay::new(delay).await;
+            delay = std::cmp::min(delay * 2, self.policy.max_delay);
+        }
+    }
+
+    /// Tries to reconnect once if `result` failed because connection `generation` went
+    /// away, so that the failure is reported without waiting for the node to come back.
+    async fn check<R>(&self, generation: u64, result: &Result<R, Error>) {
+        if result.is_err() && !self.is_alive(generation).await {
+            if let Err(err) = self.try_reconnect(generation).await {
+                log::warn!("Failed to reconnect: {}", err);
+            }
+        }
+    }
+}
+
+/// `author_submitAndWatchExtrinsic` submits the extrinsic again when re-issued, so the
+/// subscription is closed instead.
+fn is_resumable(subscribe_method: &str) -> bool {
+    subscribe_method != "author_submitAndWatchExtrinsic"
+}
+
+struct ActiveSubscription {
+    shared: Arc<Shared>,
+    subscribe_method: &'static str,
+    params: Params,
+    unsubscribe_method: &'static str,
+    generation: u64,
+    notifications: Notifications,
+    closed: bool,
+}
+
+impl ActiveSubscription {
+    /// Waits for the next notification, returns `None` if the connection was lost.
+    async fn next(&mut self) -> Option<Result<JsonValue, Error>> {
+        loop {
+            let health_check = Delay::new(self.shared.policy.health_check_interval);
+            match future::select(self.notifications.next(), health_check).await {
+                Either::Left((notification, _)) => return notification,
+                Either::Right(_) => {
+                    if !self.shared.is_alive(self.generation).await {
+                        return None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Re-issues the subscription on a new connection.
+    async fn resubscribe(&mut self) -> Result<(), Error> {
+        let (transport, generation) = self.shared.reconnect(self.generation).await?;
+        self.generation = generation;
+        self.notifications = transport
+            .subscribe(
+                self.subscribe_method,
+                self.params.clone(),
+                self.unsubscribe_method,
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn into_notifications(self) -> Notifications {
+        let notifications = stream::unfold(self, |mut subscription| {
+            async move {
+                if subscription.closed {
+                    return None
+                }
+                if let Some(notification) = subscription.next().await {
+                    return Some((notification, subscription))
+                }
+                log::warn!(
+                    "Connection lost for subscription `{}`",
+                    subscription.subscribe_method
+                );
+                if !is_resumable(subscription.subscribe_method) {
+                    subscription.closed = true;
+                    return Some((Err(Error::SubscriptionGap), subscription))
+                }
+                match subscription.resubscribe().await {
+                    Ok(()) => Some((Err(Error::SubscriptionGap), subscription)),
+                    Err(err) => {
+                        subscription.closed = true;
+                        Some((Err(err), subscription))
+                    }
+                }
+            }
+        });
+        Box::pin(notifications)
+    }
+}
+
+/// Transport which re-opens the connection with a backoff when the node goes away.
+///
+/// Active subscriptions are re-issued on the new connection, and yield
+/// `Error::SubscriptionGap` to signal that notifications may have been missed in between.
+/// The subscription carries on after the gap, so consumers should not stop at the first
+/// `Err` item. A failed request makes a single attempt to reconnect and then returns its
+/// error, instead of waiting for the node to come back.
+#[derive(Clone)]
+pub struct ReconnectingTransport {
+    shared: Arc<Shared>,
+}
+
+impl ReconnectingTransport {
+    /// Opens the first connection with `connect`, which is called again for every
+    /// reconnection.
+    pub async fn new<F>(connect: F, policy: ReconnectPolicy) -> Result<Self, Error>
+    where
+        F: Fn() -> RpcFuture<Arc<dyn Transport>> + Send + Sync + 'static,
+    {
+        let transport = connect().await?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                connect: Box::new(connect),
+                policy,
+                connection: Mutex::new(Connection {
+                    transport,
+                    generation: 0,
+                }),
+            }),
+        })
+    }
+
+    /// Connects to the websocket endpoint at `url`.
+    ///
+    /// Fails with `Error::UnsupportedScheme` for any url other than `ws://` or `wss://`.
+    pub async fn connect_ws(url: &str, policy: ReconnectPolicy) -> Result<Self, Error> {
+        match url::Url::parse(url)?.scheme() {
+            "ws" | "wss" => (),
+            scheme => return Err(Error::UnsupportedScheme(scheme.to_string())),
+        }
+        let url = url.to_string();
+        let connect = move || -> RpcFuture<Arc<dyn Transport>> {
+            let url = url.clone();
+            Box::pin(async move {
+                let transport = WsTransport::connect(&url).await?;
+                Ok(Arc::new(transport) as Arc<dyn Transport>)
+            })
+        };
+        Self::new(connect, policy).await
+    }
+}
+
+impl Transport for ReconnectingTransport {
+    fn request(&self, method: &'static str, params: Params) -> RpcFuture<JsonValue> {
+        let shared = self.shared.clone();
+        Box::pin(async move {
+            let (transport, generation) = shared.current().await;
+            let result = transport.request(method, params).await;
+            shared.check(generation, &result).await;
+            result
+        })
+    }
+
+    fn subscribe(
+        &self,
+        subscribe_method: &'static str,
+        params: Params,
+        unsubscribe_method: &'static str,
+    ) -> RpcFuture<Notifications> {
+        let shared = self.shared.clone();
+        Box::pin(async move {
+            let (transport, generation) = shared.current().await;
+            let result = transport
+                .subscribe(subscribe_method, params.clone(), unsubscribe_method)
+                .await;
+            shared.check(generation, &result).await;
+            let subscription = ActiveSubscription {
+                shared,
+                subscribe_method,
+                params,
+                unsubscribe_method,
+                generation,
+                notifications: result?,
+                closed: false,
+            };
+            Ok(subscription.into_notifications())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::Mutex as StdMutex,
+    };
+
+    use serde_json::json;
+
+    use sp_runtime::traits::Header as _;
+
+    use super::*;
+    use crate::{
+        frame::system::System,
+        rpc::{
+            MockTransport,
+            Rpc,
+        },
+        DefaultNodeRuntime as Runtime,
+    };
+
+    type Header = <Runtime as System>::Header;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+            max_attempts: Some(3),
+            health_check_interval: Duration::from_millis(50),
+        }
+    }
+
+    fn header(number: u32) -> Header {
+        Header::new(
+            number,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+    }
+
+    /// Hands out the given transports, one per connection.
+    fn connections(
+        transports: Vec<MockTransport>,
+    ) -> impl Fn() -> RpcFuture<Arc<dyn Transport>> + Send + Sync + 'static {
+        let transports = StdMutex::new(VecDeque::from(transports));
+        move || {
+            let transport = transports.lock().unwrap().pop_front();
+            Box::pin(future::ready(
+                transport
+                    .map(|t| Arc::new(t) as Arc<dyn Transport>)
+                    .ok_or_else(|| Error::from("Connection refused")),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_resubscribes_after_connection_lost() {
+        let first = MockTransport::new();
+        first.notify("chain_subscribeNewHeads", vec![json!(1)]);
+        let second = MockTransport::new();
+        second.notify("chain_subscribeNewHeads", vec![json!(3)]);
+
+        let notifications = async_std::task::block_on(async {
+            let transport =
+                ReconnectingTransport::new(connections(vec![first, second]), policy())
+                    .await
+                    .unwrap();
+            let notifications = transport
+                .subscribe(
+                    "chain_subscribeNewHeads",
+                    Params::None,
+                    "chain_unsubscribeNewHeads",
+                )
+                .await
+                .unwrap();
+            notifications.collect::<Vec<_>>().await
+        });
+
+        assert_eq!(notifications.len(), 4);
+        assert_eq!(notifications[0].as_ref().unwrap(), &json!(1));
+        assert!(match notifications[1] {
+            Err(Error::SubscriptionGap) => true,
+            _ => false,
+        });
+        assert_eq!(notifications[2].as_ref().unwrap(), &json!(3));
+        // the second connection closes as well, and no third one is available
+        assert!(match notifications[3] {
+            Err(Error::SubscriptionGap) => false,
+            Err(_) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_typed_subscription_continues_after_gap() {
+        let first = MockTransport::new();
+        first.notify("chain_subscribeNewHeads", vec![header(1)]);
+        let second = MockTransport::new();
+        second.notify("chain_subscribeNewHeads", vec![header(2)]);
+
+        let headers = async_std::task::block_on(async {
+            let transport =
+                ReconnectingTransport::new(connections(vec![first, second]), policy())
+                    .await
+                    .unwrap();
+            let mut headers = Rpc::<Runtime>::new(transport)
+                .subscribe_blocks()
+                .await
+                .unwrap();
+            let mut items = Vec::new();
+            for _ in 0..3 {
+                items.push(headers.next().await.unwrap());
+            }
+            items
+        });
+
+        assert_eq!(headers[0].as_ref().unwrap().number, 1);
+        assert!(match headers[1] {
+            Err(Error::SubscriptionGap) => true,
+            _ => false,
+        });
+        assert_eq!(headers[2].as_ref().unwrap().number, 2);
+    }
+
+    #[test]
+    fn test_connect_ws_rejects_http() {
+        let result = async_std::task::block_on(ReconnectingTransport::connect_ws(
+            "http://127.0.0.1:9933",
+            policy(),
+        ));
+
+        assert!(match result {
+            Err(Error::UnsupportedScheme(scheme)) => scheme == "http",
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_does_not_resubmit_extrinsic() {
+        let first = MockTransport::new();
+        first.notify("author_submitAndWatchExtrinsic", vec![json!("ready")]);
+
+        let notifications = async_std::task::block_on(async {
+            let transport =
+                ReconnectingTransport::new(connections(vec![first]), policy())
+                    .await
+                    .unwrap();
+            let notifications = transport
+                .subscribe(
+                    "author_submitAndWatchExtrinsic",
+                    Params::None,
+                    "author_unwatchExtrinsic",
+                )
+                .await
+                .unwrap();
+            notifications.collect::<Vec<_>>().await
+        });
+
+        assert_eq!(notifications.len(), 2);
+        assert!(match notifications[1] {
+            Err(Error::SubscriptionGap) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_requests_fail_while_reconnecting() {
+        let first = MockTransport::new();
+        first.notify("chain_subscribeNewHeads", vec![json!(1)]);
+        let policy = ReconnectPolicy {
+            max_attempts: None,
+            ..policy()
+        };
+
+        let result = async_std::task::block_on(async {
+            let transport = ReconnectingTransport::new(connections(vec![first]), policy)
+                .await
+                .unwrap();
+            let mut notifications = transport
+                .subscribe(
+                    "chain_subscribeNewHeads",
+                    Params::None,
+                    "chain_unsubscribeNewHeads",
+                )
+                .await
+                .unwrap();
+            assert_eq!(notifications.next().await.unwrap().unwrap(), json!(1));
+            // the subscription retries the refused connection forever
+            let resubscribe = notifications.next();
+            let request = Box::pin(async {
+                Delay::new(Duration::from_millis(20)).await;
+                transport.request("chain_getBlockHash", Params::None).await
+            });
+            let timeout = Delay::new(Duration::from_secs(5));
+            match future::select(future::select(resubscribe, request), timeout).await {
+                Either::Left((Either::Right((result, _)), _)) => Some(result),
+                _ => None,
+            }
+        });
+
+        assert!(match result {
+            Some(Err(_)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_reconnects_when_request_fails() {
+        let first = MockTransport::new();
+        let second = MockTransport::new();
+        second.respond("chain_getBlockHash", json!("0x01"));
+
+        let results = async_std::task::block_on(async {
+            let transport =
+                ReconnectingTransport::new(connections(vec![first, second]), policy())
+                    .await
+                    .unwrap();
+            let first = transport.request("chain_getBlockHash", Params::None).await;
+            let second = transport.request("chain_getBlockHash", Params::None).await;
+            (first, second)
+        });
+
+        assert!(results.0.is_err());
+        assert_eq!(results.1.unwrap(), json!("0x01"));
+    }
+}
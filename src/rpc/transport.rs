@@ -0,0 +1,255 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transports used by `Rpc` to talk to a substrate node.
+
+use std::pin::Pin;
+
+use futures::{
+    future::{
+        self,
+        Future,
+    },
+    stream::{
+        self,
+        Stream,
+    },
+};
+use jsonrpsee::{
+    core::common::Params,
+    Client,
+};
+use serde_json::Value as JsonValue;
+
+use crate::error::Error;
+
+/// Future returned by a `Transport`.
+pub type RpcFuture<T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send>>;
+
+/// Raw notifications pushed by the node for an active subscription.
+pub type Notifications = Pin<Box<dyn Stream<Item = Result<JsonValue, Error>> + Send>>;
+
+/// A JSON-RPC transport.
+///
+/// Implementations only deal with raw json values, decoding into concrete types is done by
+/// `Rpc`.
+pub trait Transport: Send + Sync {
+    /// Calls `method` and returns the `result` of the response.
+    fn request(&self, method: &'static str, params: Params) -> RpcFuture<JsonValue>;
+
+    /// Calls `subscribe_method` and returns the stream of notifications for the new
+    /// subscription.
+    fn subscribe(
+        &self,
+        subscribe_method: &'static str,
+        params: Params,
+        unsubscribe_method: &'static str,
+    ) -> RpcFuture<Notifications>;
+}
+
+/// Websocket transport, supports both requests and subscriptions.
+#[derive(Clone)]
+pub struct WsTransport {
+    client: Client,
+}
+
+impl WsTransport {
+    /// Connects to the websocket endpoint at `url`.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let raw_client = jsonrpsee::ws::ws_raw_client(url).await?;
+        Ok(Self {
+            client: raw_client.into(),
+        })
+    }
+}
+
+impl Transport for WsTransport {
+    fn request(&self, method: &'static str, params: Params) -> RpcFuture<JsonValue> {
+        let client = self.client.clone();
+        Box::pin(async move { Ok(client.request(method, params).await?) })
+    }
+
+    fn subscribe(
+        &self,
+        subscribe_method: &'static str,
+        params: Params,
+        unsubscribe_method: &'static str,
+    ) -> RpcFuture<Notifications> {
+        let client = self.client.clone();
+        Box::pin(async move {
+            let subscription = client
+                .subscribe::<JsonValue>(subscribe_method, params, unsubscribe_method)
+                .await?;
+            let notifications = stream::unfold(subscription, |mut subscription| {
+                async move {
+                    let notification = subscription.next().await;
+                    Some((Ok(notification), subscription))
+                }
+            });
+            Ok(Box::pin(notifications) as Notifications)
+        })
+    }
+}
+
+/// Plain HTTP transport.
+///
+/// HTTP is request/response only, so subscriptions fail with
+/// `Error::SubscriptionsUnsupported`.
+#[derive(Clone)]
+pub struct HttpTransport {
+    client: Client,
+}
+
+impl HttpTransport {
+    /// Creates a transport for the HTTP endpoint at `url`.
+    pub fn new(url: &str) -> Self {
+        let raw_client = jsonrpsee::http::http_raw_client(url);
+        Self {
+            client: raw_client.into(),
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn request(&self, method: &'static str, params: Params) -> RpcFuture<JsonValue> {
+        let client = self.client.clone();
+        Box::pin(async move { Ok(client.request(method, params).await?) })
+    }
+
+    fn subscribe(
+        &self,
+        subscribe_method: &'static str,
+        _params: Params,
+        _unsubscribe_method: &'static str,
+    ) -> RpcFuture<Notifications> {
+        Box::pin(future::err(Error::SubscriptionsUnsupported(
+            subscribe_method.to_string(),
+        )))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        io::{
+            BufRead,
+            BufReader,
+            Read,
+            Write,
+        },
+        net::TcpListener,
+        thread,
+    };
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        rpc::Rpc,
+        DefaultNodeRuntime as Runtime,
+    };
+
+    /// Starts an HTTP server which answers each incoming JSON-RPC request with the next
+    /// canned `result`, and returns its url.
+    pub(crate) fn http_stub(results: Vec<JsonValue>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind stub");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for result in results {
+                let (stream, _) = listener.accept().expect("Failed to accept");
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).expect("Failed to read header");
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break
+                    }
+                    let lower = line.to_ascii_lowercase();
+                    if lower.starts_with("content-length:") {
+                        content_length = lower[15..].trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).expect("Failed to read body");
+                let request: JsonValue = serde_json::from_slice(&body).unwrap();
+
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "result": result,
+                    "id": request["id"],
+                })
+                .to_string();
+                let mut stream = reader.into_inner();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .expect("Failed to write response");
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_http_transport_request() {
+        let hash = "0x0101010101010101010101010101010101010101010101010101010101010101";
+        let url = http_stub(vec![json!(hash)]);
+        let result = async_std::task::block_on(async move {
+            HttpTransport::new(&url)
+                .request("chain_getBlockHash", Params::None)
+                .await
+        });
+
+        assert_eq!(result.unwrap(), json!(hash));
+    }
+
+    #[test]
+    fn test_http_transport_rejects_subscriptions() {
+        let result = async_std::task::block_on(async move {
+            HttpTransport::new("http://127.0.0.1:9933")
+                .subscribe(
+                    "chain_subscribeNewHeads",
+                    Params::None,
+                    "chain_unsubscribeNewHeads",
+                )
+                .await
+        });
+
+        assert!(match result {
+            Err(Error::SubscriptionsUnsupported(method)) => {
+                method == "chain_subscribeNewHeads"
+            }
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_rpc_connect_http() {
+        let hash = sp_core::H256::repeat_byte(1);
+        let url = http_stub(vec![json!(hash)]);
+        let result = async_std::task::block_on(async move {
+            let rpc = Rpc::<Runtime>::connect(&url).await?;
+            rpc.block_hash(None).await
+        });
+
+        assert_eq!(result.unwrap(), Some(hash));
+    }
+}
@@ -25,14 +25,39 @@ use sp_runtime::{
     OpaqueExtrinsic,
 };
 
-use crate::frame::{
-    asset_registry::AssetRegistry,
-    balances::Balances,
-    contracts::Contracts,
-    identity::Identity,
-    system::System,
+use crate::{
+    events::{
+        EventsDecoder,
+        EventsError,
+        PrimitiveKind,
+    },
+    extrinsic::{
+        DefaultExtra,
+        SignedExtra,
+    },
+    frame::{
+        asset_registry::AssetRegistry,
+        balances::Balances,
+        contracts::Contracts,
+        identity::Identity,
+        system::System,
+    },
 };
 
+/// A runtime, contributing the types used in the events of its modules and the signed
+/// extensions of its transactions.
+///
+/// The `System` and `Balances` types are registered by the `EventsDecoder` itself, the
+/// other modules of the runtime register theirs here, e.g. with
+/// `Identity::register_event_types`.
+pub trait Runtime: System + Balances {
+    /// The signed extensions of the runtime's transactions.
+    type SignedExtra: SignedExtra<Self>;
+
+    /// Registers the sizes of the event argument types of the runtime's modules.
+    fn register_type_sizes(decoder: &mut EventsDecoder<Self>) -> Result<(), EventsError>;
+}
+
 /// Concrete type definitions compatible with those in the default substrate `node_runtime`
 ///
 /// # Note
@@ -62,3 +87,28 @@ impl Contracts for DefaultNodeRuntime {}
 impl Identity for DefaultNodeRuntime {}
 
 impl AssetRegistry for DefaultNodeRuntime {}
+
+impl Runtime for DefaultNodeRuntime {
+    type SignedExtra = DefaultExtra<Self>;
+
+    fn register_type_sizes(decoder: &mut EventsDecoder<Self>) -> Result<(), EventsError> {
+        // node runtime modules without client support
+        decoder.register_type::<u32>("ReferendumIndex", PrimitiveKind::Uint)?;
+        decoder.register_type_size::<[u8; 16]>("Kind")?;
+        decoder.register_type_size::<[u8; 32]>("AuthorityId")?;
+        decoder.register_type::<u32>("AccountIndex", PrimitiveKind::Uint)?;
+        decoder.register_type::<u32>("SessionIndex", PrimitiveKind::Uint)?;
+        decoder.register_type::<u32>("PropIndex", PrimitiveKind::Uint)?;
+        decoder.register_type::<u32>("ProposalIndex", PrimitiveKind::Uint)?;
+        decoder.register_type::<u32>("AuthorityIndex", PrimitiveKind::Uint)?;
+        decoder.register_type::<u64>("AuthorityWeight", PrimitiveKind::Uint)?;
+        decoder.register_type::<u32>("MemberCount", PrimitiveKind::Uint)?;
+        // VoteThreshold enum index
+        decoder.register_type::<u8>("VoteThreshold", PrimitiveKind::Uint)?;
+
+        <Self as Contracts>::register_event_types(decoder)?;
+        <Self as Identity>::register_event_types(decoder)?;
+        <Self as AssetRegistry>::register_event_types(decoder)?;
+        Ok(())
+    }
+}
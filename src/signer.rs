@@ -0,0 +1,173 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signing of transactions, with local keys or by external signers.
+
+use std::{
+    future::Future,
+    pin::Pin,
+};
+
+use futures::future;
+use sp_core::Pair;
+use sp_runtime::traits::{
+    IdentifyAccount,
+    Verify,
+};
+
+use crate::{
+    error::Error,
+    frame::system::System,
+};
+
+/// A signature being created by a `Signer`.
+pub type SignFuture<'a, S> = Pin<Box<dyn Future<Output = Result<S, Error>> + Send + 'a>>;
+
+/// Signs transactions on behalf of an account.
+///
+/// Implemented for every `Pair`, so keys held in memory can be used directly. Keys which
+/// must not leave a hardware module or a remote signing service are used by implementing
+/// this trait for a client of that service.
+pub trait Signer<T: System, S>: Send + Sync {
+    /// Returns the account transactions are signed for.
+    fn account_id(&self) -> T::AccountId;
+
+    /// Signs the payload of a transaction.
+    ///
+    /// Payloads longer than 256 bytes are already hashed, as required by the runtime.
+    fn sign<'a>(&'a self, payload: &'a [u8]) -> SignFuture<'a, S>;
+}
+
+impl<T, S, P> Signer<T, S> for P
+where
+    T: System,
+    P: Pair,
+    S: Verify + From<P::Signature> + Send,
+    S::Signer: From<P::Public> + IdentifyAccount<AccountId = T::AccountId>,
+{
+    fn account_id(&self) -> T::AccountId {
+        S::Signer::from(self.public()).into_account()
+    }
+
+    fn sign<'a>(&'a self, payload: &'a [u8]) -> SignFuture<'a, S> {
+        let signature = Pair::sign(self, payload).into();
+        Box::pin(future::ready(Ok(signature)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use codec::Encode;
+    use sp_core::ed25519;
+    use sp_keyring::{
+        AccountKeyring,
+        Ed25519Keyring,
+    };
+    use sp_runtime::{
+        generic::SignedPayload,
+        MultiSignature,
+    };
+
+    use super::*;
+    use crate::{
+        extrinsic::{
+            self,
+            DefaultExtra,
+            SignedExtra,
+        },
+        frame::balances,
+        tests::{
+            genesis_hash,
+            mock_client,
+            runtime_version,
+        },
+        DefaultNodeRuntime as Runtime,
+        MockTransport,
+    };
+
+    /// Stands in for a remote signing service, recording the payloads it signs.
+    struct RemoteSigner {
+        key: ed25519::Pair,
+        payloads: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl Signer<Runtime, MultiSignature> for RemoteSigner {
+        fn account_id(&self) -> <Runtime as System>::AccountId {
+            Ed25519Keyring::Alice.to_account_id()
+        }
+
+        fn sign<'a>(&'a self, payload: &'a [u8]) -> SignFuture<'a, MultiSignature> {
+            Box::pin(async move {
+                self.payloads.lock().unwrap().push(payload.to_vec());
+                Ok(Pair::sign(&self.key, payload).into())
+            })
+        }
+    }
+
+    #[test]
+    fn test_pair_signer() {
+        let pair = AccountKeyring::Alice.pair();
+        let account_id = Signer::<Runtime, MultiSignature>::account_id(&pair);
+        assert_eq!(account_id, AccountKeyring::Alice.to_account_id());
+
+        let signature: MultiSignature =
+            async_std::task::block_on(Signer::<Runtime, _>::sign(&pair, b"payload"))
+                .unwrap();
+        assert!(signature.verify(&b"payload"[..], &account_id));
+    }
+
+    #[test]
+    fn test_mock_external_signer() {
+        let transport = MockTransport::new();
+        let signer = RemoteSigner {
+            key: Ed25519Keyring::Alice.pair(),
+            payloads: Mutex::new(Vec::new()),
+        };
+        let dest = AccountKeyring::Bob.to_account_id();
+        let transfer = || balances::transfer::<Runtime>(dest.clone().into(), 10_000);
+        let extrinsic = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            let xt = client.xt(signer, Some(0)).await?;
+            let extrinsic = xt.create_and_sign(transfer()).await?;
+            let call = xt
+                .metadata()
+                .module_with_calls("Balances")
+                .and_then(|module| module.call("transfer", transfer().args))?;
+            Ok::<_, Error>((extrinsic, call, xt))
+        });
+
+        let (extrinsic, call, xt) = extrinsic.expect("Extrinsic should be signed");
+        let extra = DefaultExtra::<Runtime>::new(
+            runtime_version().spec_version,
+            0,
+            genesis_hash(),
+        );
+        // the signer is given the payload of the call and the expected extra
+        let payload = SignedPayload::new(call.clone(), extra.extra()).unwrap();
+        let payloads = xt.signer().payloads.lock().unwrap();
+        assert_eq!(*payloads, vec![payload.encode()]);
+        // and produces the extrinsic a local key would
+        let signed = extrinsic::create_and_sign::<Runtime, _, _, MultiSignature, _>(
+            &Ed25519Keyring::Alice.pair(),
+            call,
+            extra,
+        );
+        let signed = async_std::task::block_on(signed).unwrap();
+        assert_eq!(extrinsic.encode(), signed.encode());
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Policies computing the tip paid to prioritise a transaction in the pool.
+
+use std::cmp;
+
+use sp_runtime::traits::{
+    Saturating,
+    UniqueSaturatedFrom,
+};
+
+use crate::{
+    frame::balances::Balances,
+    rpc::RpcFuture,
+    runtimes::Runtime,
+    Client,
+};
+
+/// Computes the tip of a transaction when it is submitted.
+pub trait TipPolicy<T: Balances, S>: Send + Sync {
+    /// Returns the tip for a transaction submitted through `client`.
+    fn tip(&self, client: &Client<T, S>) -> RpcFuture<T::Balance>;
+}
+
+/// Tips more the more extrinsics are waiting in the transaction pool.
+///
+/// The tip is `base + per_pending * pending`, capped at `max`.
+pub struct CongestionTip<T: Balances> {
+    base: T::Balance,
+    per_pending: T::Balance,
+    max: T::Balance,
+}
+
+impl<T: Balances> Clone for CongestionTip<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.base, self.per_pending, self.max)
+    }
+}
+
+impl<T: Balances> CongestionTip<T> {
+    /// Creates a new congestion tip policy.
+    pub fn new(base: T::Balance, per_pending: T::Balance, max: T::Balance) -> Self {
+        Self {
+            base,
+            per_pending,
+            max,
+        }
+    }
+
+    /// The tip while `pending` extrinsics are waiting in the pool.
+    pub fn tip_for(&self, pending: usize) -> T::Balance {
+        let pending = <T::Balance as UniqueSaturatedFrom<u64>>::unique_saturated_from(
+            pending as u64,
+        );
+        let tip = self
+            .base
+            .saturating_add(self.per_pending.saturating_mul(pending));
+        cmp::min(tip, self.max)
+    }
+}
+
+impl<T, S> TipPolicy<T, S> for CongestionTip<T>
+where
+    T: Runtime + Send + Sync + 'static,
+    S: 'static,
+{
+    fn tip(&self, client: &Client<T, S>) -> RpcFuture<T::Balance> {
+        let client = client.clone();
+        let policy = self.clone();
+        Box::pin(async move {
+            let pending = client.pending_extrinsics().await?;
+            Ok(policy.tip_for(pending.len()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_core::Bytes;
+
+    use super::*;
+    use crate::{
+        rpc::MockTransport,
+        tests::mock_client,
+        DefaultNodeRuntime as Runtime,
+    };
+
+    #[test]
+    fn test_congestion_tip() {
+        let transport = MockTransport::new();
+        let policy = CongestionTip::<Runtime>::new(10, 5, 30);
+        let tips = async_std::task::block_on(async {
+            let client = mock_client(&transport).await;
+            transport
+                .respond("author_pendingExtrinsics", Vec::<Bytes>::new())
+                .respond("author_pendingExtrinsics", vec![Bytes(vec![0]); 3])
+                .respond("author_pendingExtrinsics", vec![Bytes(vec![0]); 10]);
+            let mut tips = Vec::new();
+            for _ in 0..3 {
+                tips.push(policy.tip(&client).await?);
+            }
+            Ok::<_, crate::Error>(tips)
+        });
+
+        assert_eq!(tips.expect("Tips should be computed"), vec![10, 25, 30]);
+    }
+}